@@ -0,0 +1,273 @@
+use crate::{
+    assets::{mesh, Mesh, Texture},
+    ecs,
+    error::Error,
+    import,
+    prefab::Prefab,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::BufReader,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+};
+use vulkano::{
+    device::{DeviceOwned, Queue},
+    format::Format,
+    sampler::Sampler,
+};
+
+pub const WHITE_TEXTURE: &str = "#white";
+pub const CUBE_MESH: &str = "#cube";
+
+pub struct Library {
+    pub queue: Arc<Queue>,
+    pub sampler: Arc<Sampler>,
+    pub format: Format,
+    pub root: PathBuf,
+    meshes: RwLock<HashMap<String, Arc<Mesh>>>,
+    textures: RwLock<HashMap<String, Arc<Texture>>>,
+    data_textures: RwLock<HashMap<String, Arc<Texture>>>,
+    prefabs: RwLock<HashMap<String, Arc<Prefab>>>,
+    imported: RwLock<HashSet<String>>,
+}
+
+impl Library {
+    pub fn new(
+        queue: Arc<Queue>,
+        sampler: Arc<Sampler>,
+        format: Format,
+        root: PathBuf,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            queue,
+            sampler,
+            format,
+            root,
+            meshes: RwLock::new(HashMap::new()),
+            textures: RwLock::new(HashMap::new()),
+            data_textures: RwLock::new(HashMap::new()),
+            prefabs: RwLock::new(HashMap::new()),
+            imported: RwLock::new(HashSet::new()),
+        })
+    }
+
+    pub fn mesh(&self, path: &str) -> Result<Arc<Mesh>, Error> {
+        if let Some(mesh) = self.meshes.read().unwrap().get(path) {
+            return Ok(mesh.clone());
+        }
+
+        if path == CUBE_MESH {
+            let mesh = Mesh::new(self.queue.clone(), mesh::cube(1.0))?;
+
+            self.insert_mesh(path, mesh.clone());
+
+            return Ok(mesh);
+        }
+
+        if let Some((file, _)) = path.split_once('#') {
+            self.import(file)?;
+
+            return self
+                .meshes
+                .read()
+                .unwrap()
+                .get(path)
+                .cloned()
+                .ok_or_else(|| Error::MissingAsset(path.to_string()));
+        }
+
+        let reader = BufReader::new(File::open(self.root.join(path))?);
+        let mesh = Mesh::from_obj(self.queue.clone(), reader)?;
+
+        self.insert_mesh(path, mesh.clone());
+
+        Ok(mesh)
+    }
+
+    pub fn texture(&self, path: &str) -> Result<Arc<Texture>, Error> {
+        if let Some(texture) = self.textures.read().unwrap().get(path) {
+            return Ok(texture.clone());
+        }
+
+        if let (false, Some((file, _))) = (path == WHITE_TEXTURE, path.split_once('#')) {
+            self.import(file)?;
+
+            return self
+                .textures
+                .read()
+                .unwrap()
+                .get(path)
+                .cloned()
+                .ok_or_else(|| Error::MissingAsset(path.to_string()));
+        }
+
+        let texture = if path == WHITE_TEXTURE {
+            Texture::solid(
+                [255; 4],
+                self.queue.clone(),
+                self.sampler.clone(),
+                self.format,
+            )?
+        } else {
+            let reader = BufReader::new(File::open(self.root.join(path))?);
+
+            Texture::from_png(
+                reader,
+                self.queue.clone(),
+                self.sampler.clone(),
+                self.format,
+            )?
+        };
+
+        self.insert_texture(path, texture.clone());
+
+        Ok(texture)
+    }
+
+    // Normal, metallic-roughness and occlusion maps hold data rather than color, so they're
+    // loaded without the sRGB decode `format` would apply.
+    pub fn data_texture(&self, path: &str) -> Result<Arc<Texture>, Error> {
+        if path == WHITE_TEXTURE {
+            return self.texture(path);
+        }
+
+        if let Some(texture) = self.data_textures.read().unwrap().get(path) {
+            return Ok(texture.clone());
+        }
+
+        if let Some((file, _)) = path.split_once('#') {
+            self.import(file)?;
+
+            return self
+                .data_textures
+                .read()
+                .unwrap()
+                .get(path)
+                .cloned()
+                .ok_or_else(|| Error::MissingAsset(path.to_string()));
+        }
+
+        let reader = BufReader::new(File::open(self.root.join(path))?);
+        let texture = Texture::from_png(
+            reader,
+            self.queue.clone(),
+            self.sampler.clone(),
+            self.data_format(),
+        )?;
+
+        self.insert_data_texture(path, texture.clone());
+
+        Ok(texture)
+    }
+
+    pub fn data_format(&self) -> Format {
+        match self.format {
+            Format::R8G8B8A8_SRGB => Format::R8G8B8A8_UNORM,
+            Format::B8G8R8A8_SRGB => Format::B8G8R8A8_UNORM,
+            format => format,
+        }
+    }
+
+    // Render targets live alongside the other textures so materials and cameras can refer to
+    // them by path.
+    pub fn render_target(&self, path: &str, dimensions: [u32; 2]) -> Result<Arc<Texture>, Error> {
+        if let Some(texture) = self.textures.read().unwrap().get(path) {
+            return Ok(texture.clone());
+        }
+
+        let texture = Texture::target(
+            self.queue.device().clone(),
+            dimensions,
+            self.format,
+            self.sampler.clone(),
+        )?;
+
+        self.insert_texture(path, texture.clone());
+
+        Ok(texture)
+    }
+
+    pub fn prefab(&self, path: &str) -> Result<Arc<Prefab>, Error> {
+        if let Some(prefab) = self.prefabs.read().unwrap().get(path) {
+            return Ok(prefab.clone());
+        }
+
+        let reader = BufReader::new(File::open(self.root.join(path))?);
+        let prefab = Prefab::load(ecs::id(path), reader)?;
+
+        self.insert_prefab(path, prefab.clone());
+
+        Ok(prefab)
+    }
+
+    // Importing a file registers every asset in it, so it only ever needs to happen once; a
+    // path that's still missing afterwards isn't in the file.
+    fn import(&self, file: &str) -> Result<(), Error> {
+        if self.imported.read().unwrap().contains(file) {
+            return Ok(());
+        }
+
+        import::load(self, file)?;
+        self.imported.write().unwrap().insert(file.to_string());
+
+        Ok(())
+    }
+
+    pub fn insert_mesh(&self, path: &str, mesh: Arc<Mesh>) {
+        self.meshes.write().unwrap().insert(path.to_string(), mesh);
+    }
+
+    pub fn insert_texture(&self, path: &str, texture: Arc<Texture>) {
+        self.textures
+            .write()
+            .unwrap()
+            .insert(path.to_string(), texture);
+    }
+
+    pub fn insert_data_texture(&self, path: &str, texture: Arc<Texture>) {
+        self.data_textures
+            .write()
+            .unwrap()
+            .insert(path.to_string(), texture);
+    }
+
+    pub fn insert_prefab(&self, path: &str, prefab: Arc<Prefab>) {
+        self.prefabs
+            .write()
+            .unwrap()
+            .insert(path.to_string(), prefab);
+    }
+
+    pub fn mesh_path(&self, mesh: &Arc<Mesh>) -> Option<String> {
+        self.meshes
+            .read()
+            .unwrap()
+            .iter()
+            .find(|(_, m)| Arc::ptr_eq(m, mesh))
+            .map(|(path, _)| path.clone())
+    }
+
+    pub fn texture_path(&self, texture: &Arc<Texture>) -> Option<String> {
+        [&self.textures, &self.data_textures]
+            .iter()
+            .find_map(|textures| {
+                textures
+                    .read()
+                    .unwrap()
+                    .iter()
+                    .find(|(_, t)| Arc::ptr_eq(t, texture))
+                    .map(|(path, _)| path.clone())
+            })
+    }
+
+    pub fn prefab_path(&self, prefab: &Arc<Prefab>) -> Option<String> {
+        self.prefabs
+            .read()
+            .unwrap()
+            .iter()
+            .find(|(_, p)| Arc::ptr_eq(p, prefab))
+            .map(|(path, _)| path.clone())
+    }
+}
@@ -1,10 +1,51 @@
+use crate::assets::Texture;
+use cgmath::{Vector3, Vector4};
 use std::sync::Arc;
 
+pub struct PbrMaterial {
+    pub base_color: Vector4<f32>,
+    pub base_color_texture: Option<Arc<Texture>>,
+    pub metallic: f32,
+    pub metallic_texture: Option<Arc<Texture>>,
+    pub roughness: f32,
+    pub roughness_texture: Option<Arc<Texture>>,
+    pub occlusion_strength: f32,
+    pub occlusion_texture: Option<Arc<Texture>>,
+    pub emissive: Vector3<f32>,
+    pub emissive_texture: Option<Arc<Texture>>,
+}
+
+impl PbrMaterial {
+    pub fn new(base_color: Vector4<f32>, metallic: f32, roughness: f32) -> Self {
+        Self {
+            base_color,
+            base_color_texture: None,
+            metallic,
+            metallic_texture: None,
+            roughness,
+            roughness_texture: None,
+            occlusion_strength: 1.0,
+            occlusion_texture: None,
+            emissive: Vector3::new(0.0, 0.0, 0.0),
+            emissive_texture: None,
+        }
+    }
+}
+
+impl Default for PbrMaterial {
+    fn default() -> Self {
+        Self::new(Vector4::new(1.0, 1.0, 1.0, 1.0), 0.0, 1.0)
+    }
+}
+
 pub struct Material {
     pub ambient: f32,
     pub diff_strength: f32,
     pub spec_strength: f32,
     pub spec_power: u32,
+    pub normal_scale: f32,
+    pub normal_texture: Option<Arc<Texture>>,
+    pub pbr: Option<PbrMaterial>,
 }
 
 impl Material {
@@ -14,6 +55,40 @@ impl Material {
             diff_strength,
             spec_strength,
             spec_power,
+            normal_scale: 1.0,
+            normal_texture: None,
+            pbr: None,
+        })
+    }
+
+    pub fn with_normal_map(
+        ambient: f32,
+        diff_strength: f32,
+        spec_strength: f32,
+        spec_power: u32,
+        normal_texture: Arc<Texture>,
+        normal_scale: f32,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            ambient,
+            diff_strength,
+            spec_strength,
+            spec_power,
+            normal_scale,
+            normal_texture: Some(normal_texture),
+            pbr: None,
+        })
+    }
+
+    pub fn pbr(ambient: f32, pbr: PbrMaterial) -> Arc<Self> {
+        Arc::new(Self {
+            ambient,
+            diff_strength: 1.0,
+            spec_strength: 0.0,
+            spec_power: 1,
+            normal_scale: 1.0,
+            normal_texture: None,
+            pbr: Some(pbr),
         })
     }
 }
@@ -1,7 +1,16 @@
-use crate::error::Error;
-use cgmath::{InnerSpace, Vector3};
+use crate::{
+    assets::MeshData,
+    bounds::{Aabb, Sphere},
+    error::Error,
+};
+use cgmath::{InnerSpace, Vector2, Vector3, Zero};
 use obj::TexturedVertex;
-use std::{io::BufRead, sync::Arc};
+use std::{
+    collections::HashMap,
+    f32::consts::{FRAC_PI_2, PI, TAU},
+    io::BufRead,
+    sync::Arc,
+};
 use vulkano::{
     buffer::{BufferUsage, ImmutableBuffer},
     device::Queue,
@@ -41,39 +50,65 @@ impl Normal {
 
 vulkano::impl_vertex!(Normal, normal);
 
+#[derive(Default, Copy, Clone)]
+pub struct Tangent {
+    pub tangent: [f32; 4],
+}
+
+vulkano::impl_vertex!(Tangent, tangent);
+
 pub struct Mesh {
     pub vertices: Arc<ImmutableBuffer<[Vertex]>>,
     pub indices: Arc<ImmutableBuffer<[u32]>>,
     pub normals: Arc<ImmutableBuffer<[Normal]>>,
+    pub tangents: Arc<ImmutableBuffer<[Tangent]>>,
+    pub aabb: Aabb,
+    pub sphere: Sphere,
+    pub data: MeshData,
 }
 
 impl Mesh {
-    pub fn new(
-        queue: Arc<Queue>,
-        vertices: &[Vertex],
-        indices: &[u32],
-        normals: &[Normal],
-    ) -> Result<Arc<Self>, Error> {
+    pub fn new(queue: Arc<Queue>, mut data: MeshData) -> Result<Arc<Self>, Error> {
+        data.validate()?;
+
+        if data.normals.len() != data.positions.len() {
+            data.compute_smooth_normals();
+        }
+
+        if data.tangents.len() != data.positions.len() {
+            data.compute_tangents();
+        }
+
+        let aabb = Aabb::from_points(data.positions.iter().cloned())
+            .unwrap_or_else(|| Aabb::new(Vector3::zero(), Vector3::zero()));
+        let sphere = Sphere::from_points(data.positions.iter().cloned())
+            .unwrap_or_else(|| Sphere::new(Vector3::zero(), 0.0));
         let (normals, _) = ImmutableBuffer::from_iter(
-            normals.iter().cloned(),
+            data.gpu_normals(),
+            BufferUsage::vertex_buffer(),
+            queue.clone(),
+        )?;
+        let (tangents, _) = ImmutableBuffer::from_iter(
+            data.gpu_tangents(),
             BufferUsage::vertex_buffer(),
             queue.clone(),
         )?;
         let (indices, _) = ImmutableBuffer::from_iter(
-            indices.iter().cloned(),
+            data.indices.iter().cloned(),
             BufferUsage::index_buffer(),
             queue.clone(),
         )?;
-        let (vertices, _) = ImmutableBuffer::from_iter(
-            vertices.iter().cloned(),
-            BufferUsage::vertex_buffer(),
-            queue,
-        )?;
+        let (vertices, _) =
+            ImmutableBuffer::from_iter(data.vertices(), BufferUsage::vertex_buffer(), queue)?;
 
         Ok(Arc::new(Self {
             vertices,
             indices,
             normals,
+            tangents,
+            aabb,
+            sphere,
+            data,
         }))
     }
 
@@ -82,19 +117,427 @@ impl Mesh {
         R: BufRead,
     {
         let obj = obj::load_obj(reader)?;
-        let mut vertices = Vec::new();
-        let mut normals = Vec::new();
+        let mut data = MeshData::new(Vec::new(), obj.indices);
 
         for vertex in obj.vertices as Vec<TexturedVertex> {
-            vertices.push(Vertex {
-                position: vertex.position,
-                uv: [vertex.texture[0], vertex.texture[1]],
-            });
-            normals.push(Normal {
-                normal: vertex.normal,
-            });
+            data.positions.push(Vector3::from(vertex.position));
+            data.uvs
+                .push(Vector2::new(vertex.texture[0], vertex.texture[1]));
+            data.normals.push(Vector3::from(vertex.normal));
+        }
+
+        Self::new(queue, data)
+    }
+}
+
+fn grid<F>(columns: u32, rows: u32, vertex: F) -> MeshData
+where
+    F: Fn(f32, f32) -> (Vector3<f32>, Vector3<f32>),
+{
+    let mut data = MeshData::default();
+
+    for row in 0..=rows {
+        for column in 0..=columns {
+            let uv = Vector2::new(column as f32 / columns as f32, row as f32 / rows as f32);
+            let (position, normal) = vertex(uv.x, uv.y);
+
+            data.positions.push(position);
+            data.normals.push(normal);
+            data.uvs.push(uv);
+        }
+    }
+
+    for row in 0..rows {
+        for column in 0..columns {
+            let a = row * (columns + 1) + column;
+            let b = a + columns + 1;
+
+            data.indices
+                .extend_from_slice(&[a, b, b + 1, a, b + 1, a + 1]);
+        }
+    }
+
+    orient(&mut data);
+
+    data
+}
+
+fn disc(radius: f32, y: f32, segments: u32) -> MeshData {
+    let normal = Vector3::new(0.0, y.signum(), 0.0);
+    let mut data = MeshData::new(vec![Vector3::new(0.0, y, 0.0)], Vec::new());
+
+    data.normals.push(normal);
+    data.uvs.push(Vector2::new(0.5, 0.5));
+
+    for i in 0..=segments {
+        let (sin, cos) = (i as f32 / segments as f32 * TAU).sin_cos();
+
+        data.positions
+            .push(Vector3::new(sin * radius, y, cos * radius));
+        data.normals.push(normal);
+        data.uvs
+            .push(Vector2::new(0.5 + sin * 0.5, 0.5 - cos * 0.5 * y.signum()));
+    }
+
+    for i in 1..=segments {
+        data.indices.extend_from_slice(&[0, i, i + 1]);
+    }
+
+    orient(&mut data);
+
+    data
+}
+
+fn orient(data: &mut MeshData) {
+    for i in 0..data.indices.len() / 3 {
+        let triangle = [
+            data.indices[i * 3],
+            data.indices[i * 3 + 1],
+            data.indices[i * 3 + 2],
+        ];
+        let normal = triangle
+            .iter()
+            .map(|&index| data.normals[index as usize])
+            .sum::<Vector3<f32>>();
+
+        if data.face_normal(triangle).dot(normal) < 0.0 {
+            data.indices.swap(i * 3 + 1, i * 3 + 2);
+        }
+    }
+}
+
+pub fn plane(size: f32, subdivisions: u32) -> MeshData {
+    let subdivisions = subdivisions.max(1);
+
+    grid(subdivisions, subdivisions, |u, v| {
+        (
+            Vector3::new((u - 0.5) * size, 0.0, (v - 0.5) * size),
+            Vector3::unit_y(),
+        )
+    })
+}
+
+pub fn cube(size: f32) -> MeshData {
+    let faces = [
+        (Vector3::unit_x(), -Vector3::unit_z(), -Vector3::unit_y()),
+        (-Vector3::unit_x(), Vector3::unit_z(), -Vector3::unit_y()),
+        (Vector3::unit_y(), Vector3::unit_x(), Vector3::unit_z()),
+        (-Vector3::unit_y(), Vector3::unit_x(), -Vector3::unit_z()),
+        (Vector3::unit_z(), Vector3::unit_x(), -Vector3::unit_y()),
+        (-Vector3::unit_z(), -Vector3::unit_x(), -Vector3::unit_y()),
+    ];
+    let mut data = MeshData::default();
+
+    for (normal, right, down) in faces {
+        data.merge(&grid(1, 1, |u, v| {
+            (
+                (normal * 0.5 + right * (u - 0.5) + down * (v - 0.5)) * size,
+                normal,
+            )
+        }));
+    }
+
+    data
+}
+
+pub fn uv_sphere(radius: f32, segments: u32, rings: u32) -> MeshData {
+    grid(segments.max(3), rings.max(2), |u, v| {
+        let (sin_phi, cos_phi) = (u * TAU).sin_cos();
+        let (sin_theta, cos_theta) = (v * PI).sin_cos();
+        let normal = Vector3::new(sin_theta * sin_phi, cos_theta, sin_theta * cos_phi);
+
+        (normal * radius, normal)
+    })
+}
+
+pub fn icosphere(radius: f32, subdivisions: u32) -> MeshData {
+    let t = (1.0 + 5.0_f32.sqrt()) / 2.0;
+    let mut positions = [
+        [-1.0, t, 0.0],
+        [1.0, t, 0.0],
+        [-1.0, -t, 0.0],
+        [1.0, -t, 0.0],
+        [0.0, -1.0, t],
+        [0.0, 1.0, t],
+        [0.0, -1.0, -t],
+        [0.0, 1.0, -t],
+        [t, 0.0, -1.0],
+        [t, 0.0, 1.0],
+        [-t, 0.0, -1.0],
+        [-t, 0.0, 1.0],
+    ]
+    .map(|p| Vector3::from(p).normalize())
+    .to_vec();
+    #[rustfmt::skip]
+    let mut indices = vec![
+        0, 11, 5, 0, 5, 1, 0, 1, 7, 0, 7, 10, 0, 10, 11,
+        1, 5, 9, 5, 11, 4, 11, 10, 2, 10, 7, 6, 7, 1, 8,
+        3, 9, 4, 3, 4, 2, 3, 2, 6, 3, 6, 8, 3, 8, 9,
+        4, 9, 5, 2, 4, 11, 6, 2, 10, 8, 6, 7, 9, 8, 1,
+    ];
+
+    for _ in 0..subdivisions {
+        let mut midpoints = HashMap::new();
+        let mut midpoint = |a: u32, b: u32, positions: &mut Vec<Vector3<f32>>| {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                positions.push((positions[a as usize] + positions[b as usize]).normalize());
+
+                positions.len() as u32 - 1
+            })
+        };
+        let mut subdivided = Vec::with_capacity(indices.len() * 4);
+
+        for triangle in indices.chunks_exact(3) {
+            let [a, b, c] = [triangle[0], triangle[1], triangle[2]];
+            let ab = midpoint(a, b, &mut positions);
+            let bc = midpoint(b, c, &mut positions);
+            let ca = midpoint(c, a, &mut positions);
+
+            subdivided.extend_from_slice(&[a, ab, ca, b, bc, ab, c, ca, bc, ab, bc, ca]);
+        }
+
+        indices = subdivided;
+    }
+
+    let mut data = MeshData::new(positions.iter().map(|p| p * radius).collect(), indices);
+
+    data.normals = positions.clone();
+    data.uvs = positions
+        .iter()
+        .map(|p| Vector2::new(0.5 + p.x.atan2(p.z) / TAU, p.y.acos() / PI))
+        .collect();
+
+    let mut wrapped = HashMap::new();
+
+    for triangle in 0..data.indices.len() / 3 {
+        let corners = [0, 1, 2].map(|corner| triangle * 3 + corner);
+        let us = corners.map(|i| data.uvs[data.indices[i] as usize].x);
+        let max = us.iter().cloned().fold(f32::MIN, f32::max);
+
+        for (i, u) in corners.into_iter().zip(us) {
+            if max - u > 0.5 {
+                let index = data.indices[i] as usize;
+                let seam = *wrapped.entry(index).or_insert_with(|| {
+                    data.positions.push(data.positions[index]);
+                    data.normals.push(data.normals[index]);
+                    data.uvs.push(data.uvs[index] + Vector2::unit_x());
+
+                    data.positions.len() as u32 - 1
+                });
+
+                data.indices[i] = seam;
+            }
+        }
+    }
+
+    // The poles have no longitude of their own, so every triangle gets a pole in line with its
+    // other two corners.
+    for i in 0..data.indices.len() {
+        let index = data.indices[i] as usize;
+        let position = data.positions[index];
+
+        if position.x.abs() < 1e-6 && position.z.abs() < 1e-6 {
+            let triangle = i - i % 3;
+            let u = (triangle..triangle + 3)
+                .filter(|&corner| corner != i)
+                .map(|corner| data.uvs[data.indices[corner] as usize].x)
+                .sum::<f32>()
+                / 2.0;
+
+            data.positions.push(position);
+            data.normals.push(data.normals[index]);
+            data.uvs.push(Vector2::new(u, data.uvs[index].y));
+            data.indices[i] = data.positions.len() as u32 - 1;
+        }
+    }
+
+    data
+}
+
+pub fn cylinder(radius: f32, height: f32, segments: u32) -> MeshData {
+    let segments = segments.max(3);
+    let mut data = grid(segments, 1, |u, v| {
+        let (sin, cos) = (u * TAU).sin_cos();
+        let normal = Vector3::new(sin, 0.0, cos);
+
+        (
+            normal * radius + Vector3::unit_y() * (0.5 - v) * height,
+            normal,
+        )
+    });
+
+    data.merge(&disc(radius, height / 2.0, segments));
+    data.merge(&disc(radius, -height / 2.0, segments));
+
+    data
+}
+
+pub fn cone(radius: f32, height: f32, segments: u32) -> MeshData {
+    let segments = segments.max(3);
+    let mut data = grid(segments, 1, |u, v| {
+        let (sin, cos) = (u * TAU).sin_cos();
+
+        (
+            Vector3::new(sin * radius * v, (0.5 - v) * height, cos * radius * v),
+            Vector3::new(sin * height, radius, cos * height).normalize(),
+        )
+    });
+
+    data.merge(&disc(radius, -height / 2.0, segments));
+
+    data
+}
+
+pub fn capsule(radius: f32, height: f32, segments: u32, rings: u32) -> MeshData {
+    let rings = rings.max(1);
+    let rows = rings * 2 + 1;
+    let length = PI * radius + height;
+    let row = |v: f32| (v * rows as f32).round() as u32;
+    let mut data = grid(segments.max(3), rows, |u, v| {
+        let row = row(v);
+        let (theta, offset) = if row <= rings {
+            (row as f32 / rings as f32 * FRAC_PI_2, height / 2.0)
+        } else {
+            ((row - 1) as f32 / rings as f32 * FRAC_PI_2, -height / 2.0)
+        };
+        let (sin_phi, cos_phi) = (u * TAU).sin_cos();
+        let (sin_theta, cos_theta) = theta.sin_cos();
+        let normal = Vector3::new(sin_theta * sin_phi, cos_theta, sin_theta * cos_phi);
+
+        (normal * radius + Vector3::unit_y() * offset, normal)
+    });
+
+    for (position, uv) in data.positions.iter().zip(&mut data.uvs) {
+        let y = position.y;
+        let arc = |y: f32| radius * (y / radius).clamp(-1.0, 1.0).acos();
+
+        uv.y = if y >= height / 2.0 {
+            arc(y - height / 2.0)
+        } else if y <= -height / 2.0 {
+            arc(y + height / 2.0) + height
+        } else {
+            radius * FRAC_PI_2 + height / 2.0 - y
+        } / length;
+    }
+
+    data
+}
+
+pub fn torus(radius: f32, tube_radius: f32, segments: u32, sides: u32) -> MeshData {
+    grid(segments.max(3), sides.max(3), |u, v| {
+        let (sin_phi, cos_phi) = (u * TAU).sin_cos();
+        let (sin_theta, cos_theta) = (v * TAU).sin_cos();
+        let normal = Vector3::new(cos_theta * sin_phi, sin_theta, cos_theta * cos_phi);
+
+        (
+            Vector3::new(sin_phi, 0.0, cos_phi) * radius + normal * tube_radius,
+            normal,
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `center` maps a position to the point its normal should point away from.
+    fn check<F>(data: &MeshData, max_u: f32, center: F)
+    where
+        F: Fn(Vector3<f32>) -> Vector3<f32>,
+    {
+        data.validate().unwrap();
+        assert_eq!(data.normals.len(), data.vertex_count());
+        assert_eq!(data.uvs.len(), data.vertex_count());
+
+        for (&position, &normal) in data.positions.iter().zip(&data.normals) {
+            assert!((normal.magnitude() - 1.0).abs() < 1e-5, "{:?}", normal);
+            assert!(
+                normal.dot(position - center(position)) > 0.0,
+                "{:?}",
+                position
+            );
+        }
+
+        for triangle in data.triangles() {
+            let face = data.face_normal(triangle);
+
+            if face.magnitude() > 1e-6 {
+                let normal = triangle
+                    .map(|index| data.normals[index as usize])
+                    .iter()
+                    .sum::<Vector3<f32>>();
+
+                assert!(face.dot(normal) > 0.0, "{:?}", triangle);
+            }
+        }
+
+        for uv in &data.uvs {
+            assert!((0.0..=max_u).contains(&uv.x), "{:?}", uv);
+            assert!((0.0..=1.0).contains(&uv.y), "{:?}", uv);
+        }
+    }
+
+    fn origin(_: Vector3<f32>) -> Vector3<f32> {
+        Vector3::zero()
+    }
+
+    #[test]
+    fn closed_shapes_face_outward() {
+        check(&cube(2.0), 1.0, origin);
+        check(&uv_sphere(1.0, 16, 8), 1.0, origin);
+        check(&cylinder(1.0, 2.0, 16), 1.0, origin);
+        check(&cone(1.0, 2.0, 16), 1.0, origin);
+        check(&capsule(0.5, 1.0, 16, 4), 1.0, origin);
+        check(&torus(1.0, 0.25, 16, 8), 1.0, |p| {
+            Vector3::new(p.x, 0.0, p.z).normalize()
+        });
+    }
+
+    #[test]
+    fn icosphere_triangles_do_not_wrap_around() {
+        let data = icosphere(1.0, 2);
+
+        check(&data, 2.0, origin);
+
+        for triangle in data.triangles() {
+            let us = triangle.map(|index| data.uvs[index as usize].x);
+            let spread = us.iter().cloned().fold(f32::MIN, f32::max)
+                - us.iter().cloned().fold(f32::MAX, f32::min);
+
+            assert!(spread <= 0.5, "{:?}", us);
+        }
+    }
+
+    #[test]
+    fn planes_face_up() {
+        let data = plane(2.0, 4);
+
+        check(&data, 1.0, |p| p - Vector3::unit_y());
+        assert_eq!(data.indices.len(), 4 * 4 * 6);
+    }
+
+    #[test]
+    fn degenerate_counts_are_clamped() {
+        for count in [0, 1] {
+            let shapes = [
+                (plane(1.0, count), plane(1.0, 1)),
+                (uv_sphere(1.0, count, count), uv_sphere(1.0, 3, 2)),
+                (cylinder(1.0, 1.0, count), cylinder(1.0, 1.0, 3)),
+                (cone(1.0, 1.0, count), cone(1.0, 1.0, 3)),
+                (capsule(0.5, 1.0, count, count), capsule(0.5, 1.0, 3, 1)),
+                (torus(1.0, 0.25, count, count), torus(1.0, 0.25, 3, 3)),
+            ];
+
+            for (shape, clamped) in &shapes {
+                shape.validate().unwrap();
+                assert!(!shape.indices.is_empty());
+                assert_eq!(shape.positions, clamped.positions);
+                assert_eq!(shape.indices, clamped.indices);
+            }
         }
 
-        Self::new(queue, &vertices, &obj.indices, &normals)
+        check(&uv_sphere(1.0, 0, 0), 1.0, origin);
+        check(&capsule(0.5, 1.0, 0, 0), 1.0, origin);
+        check(&icosphere(1.0, 0), 2.0, origin);
     }
 }
@@ -0,0 +1,714 @@
+use crate::{
+    assets::mesh::{Normal, Tangent, Vertex},
+    error::Error,
+};
+use cgmath::{InnerSpace, Matrix, Matrix3, Matrix4, SquareMatrix, Vector2, Vector3, Vector4, Zero};
+use std::collections::HashMap;
+
+pub const DEFAULT_WELD_EPSILON: f32 = 1e-5;
+pub const DEFAULT_UV: Vector2<f32> = Vector2::new(0.0, 0.0);
+pub const DEFAULT_NORMAL: Vector3<f32> = Vector3::new(0.0, 1.0, 0.0);
+pub const DEFAULT_TANGENT: Vector4<f32> = Vector4::new(1.0, 0.0, 0.0, 1.0);
+pub const DEFAULT_COLOR: Vector4<f32> = Vector4::new(1.0, 1.0, 1.0, 1.0);
+
+struct Corners<'a> {
+    mesh: &'a MeshData,
+    tangents: Vec<Vector4<f32>>,
+}
+
+impl Corners<'_> {
+    fn index(&self, face: usize, vert: usize) -> usize {
+        self.mesh.indices[face * 3 + vert] as usize
+    }
+}
+
+impl bevy_mikktspace::Geometry for Corners<'_> {
+    fn num_faces(&self) -> usize {
+        self.mesh.indices.len() / 3
+    }
+
+    fn num_vertices_of_face(&self, _face: usize) -> usize {
+        3
+    }
+
+    fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.mesh.positions[self.index(face, vert)].into()
+    }
+
+    fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.mesh.normals[self.index(face, vert)].into()
+    }
+
+    fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+        self.mesh
+            .uvs
+            .get(self.index(face, vert))
+            .copied()
+            .unwrap_or(DEFAULT_UV)
+            .into()
+    }
+
+    fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+        self.tangents[face * 3 + vert] = tangent.into();
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MeshData {
+    pub positions: Vec<Vector3<f32>>,
+    pub uvs: Vec<Vector2<f32>>,
+    pub normals: Vec<Vector3<f32>>,
+    pub tangents: Vec<Vector4<f32>>,
+    pub colors: Vec<Vector4<f32>>,
+    pub indices: Vec<u32>,
+}
+
+fn merge_channel<T: Copy>(
+    channel: &mut Vec<T>,
+    other: &[T],
+    len: usize,
+    other_len: usize,
+    default: T,
+) {
+    if channel.is_empty() && other.is_empty() {
+        return;
+    }
+
+    channel.resize(len, default);
+    channel.extend_from_slice(other);
+    channel.resize(len + other_len, default);
+}
+
+fn align_channel<T: Copy>(channel: &mut Vec<T>, len: usize, default: T) {
+    if !channel.is_empty() {
+        channel.resize(len, default);
+    }
+}
+
+fn quantize(values: &[f32], epsilon: f32, key: &mut Vec<i64>) {
+    key.extend(values.iter().map(|v| (v / epsilon).round() as i64));
+}
+
+fn position_key(position: &Vector3<f32>) -> Vec<i64> {
+    let mut key = Vec::with_capacity(3);
+
+    quantize(
+        AsRef::<[f32; 3]>::as_ref(position),
+        DEFAULT_WELD_EPSILON,
+        &mut key,
+    );
+
+    key
+}
+
+impl MeshData {
+    pub fn new(positions: Vec<Vector3<f32>>, indices: Vec<u32>) -> Self {
+        Self {
+            positions,
+            indices,
+            ..Self::default()
+        }
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        if !self.indices.len().is_multiple_of(3) {
+            return Err(Error::InvalidMesh(format!(
+                "{} indices do not form whole triangles",
+                self.indices.len()
+            )));
+        }
+
+        match self
+            .indices
+            .iter()
+            .find(|&&index| index as usize >= self.positions.len())
+        {
+            Some(index) => Err(Error::InvalidMesh(format!(
+                "index {} out of range for {} vertices",
+                index,
+                self.positions.len()
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    // Pads (or truncates) every channel that is in use to one entry per position.
+    pub fn align_channels(&mut self) {
+        let len = self.positions.len();
+
+        align_channel(&mut self.uvs, len, DEFAULT_UV);
+        align_channel(&mut self.normals, len, DEFAULT_NORMAL);
+        align_channel(&mut self.tangents, len, DEFAULT_TANGENT);
+        align_channel(&mut self.colors, len, DEFAULT_COLOR);
+    }
+
+    pub fn triangles(&self) -> impl Iterator<Item = [u32; 3]> + '_ {
+        self.indices
+            .chunks_exact(3)
+            .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+    }
+
+    pub fn face_normal(&self, triangle: [u32; 3]) -> Vector3<f32> {
+        let [a, b, c] = triangle.map(|index| self.positions[index as usize]);
+
+        (b - a).cross(c - a)
+    }
+
+    pub fn vertices(&self) -> Vec<Vertex> {
+        self.positions
+            .iter()
+            .enumerate()
+            .map(|(i, position)| Vertex {
+                position: (*position).into(),
+                uv: self.uvs.get(i).copied().unwrap_or(DEFAULT_UV).into(),
+            })
+            .collect()
+    }
+
+    pub fn gpu_normals(&self) -> Vec<Normal> {
+        (0..self.positions.len())
+            .map(|i| Normal {
+                normal: self
+                    .normals
+                    .get(i)
+                    .copied()
+                    .unwrap_or(DEFAULT_NORMAL)
+                    .into(),
+            })
+            .collect()
+    }
+
+    pub fn gpu_tangents(&self) -> Vec<Tangent> {
+        (0..self.positions.len())
+            .map(|i| Tangent {
+                tangent: self
+                    .tangents
+                    .get(i)
+                    .copied()
+                    .unwrap_or(DEFAULT_TANGENT)
+                    .into(),
+            })
+            .collect()
+    }
+
+    // Accumulates by position rather than by index, so UV seams don't show up as lighting seams.
+    pub fn compute_smooth_normals(&mut self) {
+        let mut lookup = HashMap::new();
+        let slots = self
+            .positions
+            .iter()
+            .map(|position| {
+                let next = lookup.len();
+
+                *lookup.entry(position_key(position)).or_insert(next)
+            })
+            .collect::<Vec<_>>();
+        let mut normals = vec![Vector3::zero(); lookup.len()];
+
+        for triangle in self.triangles() {
+            let normal = self.face_normal(triangle);
+
+            for index in triangle {
+                normals[slots[index as usize]] += normal;
+            }
+        }
+
+        self.normals = slots
+            .into_iter()
+            .map(|slot| {
+                let normal: Vector3<f32> = normals[slot];
+
+                if normal.magnitude2() > 0.0 {
+                    normal.normalize()
+                } else {
+                    DEFAULT_NORMAL
+                }
+            })
+            .collect();
+    }
+
+    // MikkTSpace tangents, so normal maps baked by other tools line up. They're generated per
+    // corner, and a vertex is only split where its corners disagree, e.g. on a UV mirror seam.
+    pub fn compute_tangents(&mut self) {
+        if self.normals.len() != self.positions.len() {
+            self.compute_smooth_normals();
+        }
+
+        self.align_channels();
+
+        let mut corners = Corners {
+            mesh: self,
+            tangents: vec![DEFAULT_TANGENT; self.indices.len()],
+        };
+
+        // Corners MikkTSpace gives up on keep the default tangent.
+        if !self.indices.is_empty() {
+            bevy_mikktspace::generate_tangents(&mut corners);
+        }
+
+        let tangents = corners.tangents;
+        let mut lookup = HashMap::new();
+        let mut assigned = vec![false; self.positions.len()];
+
+        self.tangents = vec![DEFAULT_TANGENT; self.positions.len()];
+
+        for (corner, tangent) in tangents.into_iter().enumerate() {
+            let i = self.indices[corner] as usize;
+            let mut key = vec![i as i64];
+
+            quantize(
+                AsRef::<[f32; 4]>::as_ref(&tangent),
+                DEFAULT_WELD_EPSILON,
+                &mut key,
+            );
+
+            let index = match lookup.get(&key) {
+                Some(&index) => index,
+                None => {
+                    let index = if assigned[i] {
+                        self.duplicate(i)
+                    } else {
+                        assigned[i] = true;
+                        i as u32
+                    };
+
+                    if index as usize == self.tangents.len() {
+                        self.tangents.push(tangent);
+                    } else {
+                        self.tangents[index as usize] = tangent;
+                    }
+
+                    lookup.insert(key, index);
+                    index
+                }
+            };
+
+            self.indices[corner] = index;
+        }
+    }
+
+    fn duplicate(&mut self, i: usize) -> u32 {
+        self.positions.push(self.positions[i]);
+
+        if let Some(&uv) = self.uvs.get(i) {
+            self.uvs.push(uv);
+        }
+
+        if let Some(&normal) = self.normals.get(i) {
+            self.normals.push(normal);
+        }
+
+        if let Some(&color) = self.colors.get(i) {
+            self.colors.push(color);
+        }
+
+        self.positions.len() as u32 - 1
+    }
+
+    pub fn compute_flat_normals(&mut self) {
+        self.align_channels();
+
+        let triangles = self.triangles().collect::<Vec<_>>();
+        let corners = triangles.iter().flatten().map(|&i| i as usize);
+        let unwelded = Self {
+            positions: corners.clone().map(|i| self.positions[i]).collect(),
+            uvs: corners
+                .clone()
+                .filter_map(|i| self.uvs.get(i).copied())
+                .collect(),
+            normals: triangles
+                .iter()
+                .flat_map(|&triangle| {
+                    let normal = self.face_normal(triangle);
+                    let normal = if normal.magnitude2() > 0.0 {
+                        normal.normalize()
+                    } else {
+                        DEFAULT_NORMAL
+                    };
+
+                    [normal; 3]
+                })
+                .collect(),
+            tangents: Vec::new(),
+            colors: corners
+                .filter_map(|i| self.colors.get(i).copied())
+                .collect(),
+            indices: (0..triangles.len() as u32 * 3).collect(),
+        };
+
+        *self = unwelded;
+    }
+
+    pub fn weld(&mut self, epsilon: f32) {
+        self.align_channels();
+
+        let mut lookup = HashMap::new();
+        let mut remap = Vec::with_capacity(self.positions.len());
+        let mut welded = Self::default();
+
+        for i in 0..self.positions.len() {
+            let mut key = Vec::new();
+
+            quantize(
+                AsRef::<[f32; 3]>::as_ref(&self.positions[i]),
+                epsilon,
+                &mut key,
+            );
+
+            if let Some(&uv) = self.uvs.get(i) {
+                quantize(AsRef::<[f32; 2]>::as_ref(&uv), epsilon, &mut key);
+            }
+
+            if let Some(&normal) = self.normals.get(i) {
+                quantize(AsRef::<[f32; 3]>::as_ref(&normal), epsilon, &mut key);
+            }
+
+            if let Some(&tangent) = self.tangents.get(i) {
+                quantize(AsRef::<[f32; 4]>::as_ref(&tangent), epsilon, &mut key);
+            }
+
+            if let Some(&color) = self.colors.get(i) {
+                quantize(AsRef::<[f32; 4]>::as_ref(&color), epsilon, &mut key);
+            }
+
+            let index = *lookup.entry(key).or_insert_with(|| {
+                welded.positions.push(self.positions[i]);
+                welded.uvs.extend(self.uvs.get(i));
+                welded.normals.extend(self.normals.get(i));
+                welded.tangents.extend(self.tangents.get(i));
+                welded.colors.extend(self.colors.get(i));
+
+                welded.positions.len() as u32 - 1
+            });
+
+            remap.push(index);
+        }
+
+        welded.indices = self
+            .indices
+            .iter()
+            .map(|&index| remap[index as usize])
+            .collect();
+
+        *self = welded;
+    }
+
+    pub fn merge(&mut self, other: &MeshData) {
+        let len = self.positions.len();
+        let other_len = other.positions.len();
+
+        merge_channel(&mut self.uvs, &other.uvs, len, other_len, DEFAULT_UV);
+        merge_channel(
+            &mut self.normals,
+            &other.normals,
+            len,
+            other_len,
+            DEFAULT_NORMAL,
+        );
+        merge_channel(
+            &mut self.tangents,
+            &other.tangents,
+            len,
+            other_len,
+            DEFAULT_TANGENT,
+        );
+        merge_channel(
+            &mut self.colors,
+            &other.colors,
+            len,
+            other_len,
+            DEFAULT_COLOR,
+        );
+
+        self.positions.extend_from_slice(&other.positions);
+        self.indices
+            .extend(other.indices.iter().map(|&index| index + len as u32));
+    }
+
+    pub fn transform(&mut self, matrix: &Matrix4<f32>) {
+        let linear = Matrix3::from_cols(
+            matrix.x.truncate(),
+            matrix.y.truncate(),
+            matrix.z.truncate(),
+        );
+        let normal_matrix = linear
+            .invert()
+            .map(|inverse| inverse.transpose())
+            .unwrap_or(linear);
+        let mirrored = linear.determinant() < 0.0;
+
+        for position in &mut self.positions {
+            *position = (matrix * position.extend(1.0)).truncate();
+        }
+
+        for normal in &mut self.normals {
+            let transformed = normal_matrix * *normal;
+
+            if transformed.magnitude2() > 0.0 {
+                *normal = transformed.normalize();
+            }
+        }
+
+        for tangent in &mut self.tangents {
+            let transformed = linear * tangent.truncate();
+            let handedness = if mirrored { -tangent.w } else { tangent.w };
+
+            if transformed.magnitude2() > 0.0 {
+                *tangent = transformed.normalize().extend(handedness);
+            }
+        }
+
+        if mirrored {
+            for triangle in self.indices.chunks_exact_mut(3) {
+                triangle.swap(1, 2);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quad(mirror_u: bool) -> MeshData {
+        let positions = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+        let mut data = MeshData::new(
+            positions
+                .iter()
+                .map(|&(x, y)| Vector3::new(x, y, 0.0))
+                .collect(),
+            vec![0, 1, 2, 0, 2, 3],
+        );
+
+        data.uvs = positions
+            .iter()
+            .map(|&(x, y)| Vector2::new(if mirror_u { 1.0 - x } else { x }, 1.0 - y))
+            .collect();
+        data
+    }
+
+    // The quad's V runs down like an image's; MikkTSpace's reference cases have it run up.
+    fn flip_v(mut data: MeshData) -> MeshData {
+        for uv in &mut data.uvs {
+            uv.y = 1.0 - uv.y;
+        }
+
+        data
+    }
+
+    fn assert_near(a: Vector4<f32>, b: Vector4<f32>) {
+        assert!((a - b).magnitude() < 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn smooth_normals_ignore_uv_seams() {
+        let mut data = MeshData::new(
+            vec![
+                Vector3::new(0.0, 0.0, 0.0),
+                Vector3::new(1.0, 0.0, 0.0),
+                Vector3::new(0.0, 1.0, 0.0),
+                Vector3::new(0.0, 0.0, 0.0),
+                Vector3::new(0.0, 1.0, 0.0),
+                Vector3::new(0.0, 0.0, 1.0),
+            ],
+            vec![0, 1, 2, 3, 4, 5],
+        );
+
+        data.compute_smooth_normals();
+
+        assert_eq!(data.normals[0], data.normals[3]);
+        assert_eq!(data.normals[2], data.normals[4]);
+        assert!((data.normals[0] - Vector3::new(1.0, 0.0, 1.0).normalize()).magnitude() < 1e-5);
+        assert_eq!(data.normals[1], Vector3::unit_z());
+        assert_eq!(data.normals[5], Vector3::unit_x());
+    }
+
+    #[test]
+    fn flat_normals_unweld_every_corner() {
+        let mut data = quad(false);
+
+        data.colors = vec![DEFAULT_COLOR; 2];
+        data.compute_flat_normals();
+
+        assert_eq!(data.vertex_count(), 6);
+        assert_eq!(data.uvs.len(), 6);
+        assert_eq!(data.colors.len(), 6);
+        assert_eq!(data.uvs[4], Vector2::new(1.0, 0.0));
+        assert!(data.normals.iter().all(|&n| n == Vector3::unit_z()));
+    }
+
+    #[test]
+    fn weld_merges_identical_vertices_and_keeps_channels_aligned() {
+        let mut data = quad(false);
+
+        data.compute_flat_normals();
+        data.colors = vec![Vector4::new(1.0, 0.0, 0.0, 1.0)];
+        data.weld(DEFAULT_WELD_EPSILON);
+
+        assert_eq!(data.vertex_count(), 5);
+        assert_eq!(data.colors.len(), 5);
+        assert_eq!(data.uvs.len(), 5);
+        assert_eq!(data.indices.len(), 6);
+        assert_eq!(data.colors[0], Vector4::new(1.0, 0.0, 0.0, 1.0));
+        assert!(data.colors[1..].iter().all(|&c| c == DEFAULT_COLOR));
+
+        for triangle in data.triangles() {
+            assert!(data.face_normal(triangle).z > 0.0);
+        }
+    }
+
+    #[test]
+    fn merge_offsets_indices_and_fills_missing_channels() {
+        let mut data = MeshData::new(
+            vec![Vector3::zero(), Vector3::unit_x(), Vector3::unit_y()],
+            vec![0, 1, 2],
+        );
+
+        data.merge(&quad(false));
+
+        assert_eq!(data.vertex_count(), 7);
+        assert_eq!(data.indices, vec![0, 1, 2, 3, 4, 5, 3, 5, 6]);
+        assert_eq!(data.uvs.len(), 7);
+        assert_eq!(data.uvs[0], DEFAULT_UV);
+        assert_eq!(data.uvs[3], Vector2::new(0.0, 1.0));
+        assert!(data.normals.is_empty());
+    }
+
+    #[test]
+    fn mirrored_transforms_keep_faces_outward() {
+        let mut data = flip_v(quad(false));
+
+        data.compute_tangents();
+        data.transform(&Matrix4::from_nonuniform_scale(-1.0, 1.0, 1.0));
+
+        for triangle in data.triangles() {
+            assert!(data.face_normal(triangle).z > 0.0);
+        }
+
+        for (&normal, &tangent) in data.normals.iter().zip(&data.tangents) {
+            assert_eq!(normal, Vector3::unit_z());
+            assert_near(tangent, Vector4::new(-1.0, 0.0, 0.0, -1.0));
+        }
+    }
+
+    #[test]
+    fn out_of_range_indices_are_rejected() {
+        let mut data = quad(false);
+
+        assert!(data.validate().is_ok());
+
+        data.indices.push(0);
+        assert!(matches!(data.validate(), Err(Error::InvalidMesh(_))));
+
+        data.indices.extend_from_slice(&[1, 4]);
+        assert!(matches!(data.validate(), Err(Error::InvalidMesh(_))));
+    }
+
+    #[test]
+    fn tangents_follow_u_on_an_axis_aligned_quad() {
+        let mut data = flip_v(quad(false));
+
+        data.compute_tangents();
+
+        assert_eq!(data.tangents.len(), 4);
+
+        for &tangent in &data.tangents {
+            assert_near(tangent, Vector4::new(1.0, 0.0, 0.0, 1.0));
+        }
+    }
+
+    #[test]
+    fn mirrored_u_flips_the_handedness() {
+        let mut data = flip_v(quad(true));
+
+        data.compute_tangents();
+
+        for &tangent in &data.tangents {
+            assert_near(tangent, Vector4::new(-1.0, 0.0, 0.0, -1.0));
+        }
+    }
+
+    #[test]
+    fn vertices_on_a_mirror_seam_are_split() {
+        let mut data = MeshData::new(
+            vec![
+                Vector3::new(0.0, 0.0, 0.0),
+                Vector3::new(1.0, 0.0, 0.0),
+                Vector3::new(1.0, 1.0, 0.0),
+                Vector3::new(0.0, 1.0, 0.0),
+                Vector3::new(2.0, 0.0, 0.0),
+                Vector3::new(2.0, 1.0, 0.0),
+            ],
+            vec![0, 1, 2, 0, 2, 3, 1, 4, 5, 1, 5, 2],
+        );
+
+        data.uvs = data
+            .positions
+            .iter()
+            .map(|p| Vector2::new(if p.x > 1.5 { 0.0 } else { p.x }, p.y))
+            .collect();
+        data.compute_tangents();
+
+        assert_eq!(data.vertex_count(), 8);
+        assert_eq!(data.tangents.len(), 8);
+        assert_eq!(data.normals.len(), 8);
+
+        for (i, triangle) in data.triangles().enumerate() {
+            let expected = if i < 2 {
+                Vector4::new(1.0, 0.0, 0.0, 1.0)
+            } else {
+                Vector4::new(-1.0, 0.0, 0.0, -1.0)
+            };
+
+            for index in triangle {
+                assert_near(data.tangents[index as usize], expected);
+            }
+        }
+    }
+
+    // Reference values from MikkTSpace: the tangent follows +U and w is the sign of the
+    // bitangent relative to N x T, so flipping V alone flips w.
+    #[test]
+    fn flipped_v_flips_only_the_handedness() {
+        let mut data = quad(false);
+
+        data.compute_tangents();
+
+        for &tangent in &data.tangents {
+            assert_near(tangent, Vector4::new(1.0, 0.0, 0.0, -1.0));
+        }
+    }
+
+    #[test]
+    fn cube_faces_get_tangents_along_u() {
+        let mut data = crate::assets::mesh::cube(1.0);
+
+        data.tangents.clear();
+        data.compute_tangents();
+
+        assert_eq!(data.tangents.len(), data.vertex_count());
+
+        for triangle in data.triangles() {
+            let [a, b, c] = triangle.map(|i| i as usize);
+            let (e1, e2) = (
+                data.positions[b] - data.positions[a],
+                data.positions[c] - data.positions[a],
+            );
+            let (d1, d2) = (data.uvs[b] - data.uvs[a], data.uvs[c] - data.uvs[a]);
+            let determinant = d1.x * d2.y - d2.x * d1.y;
+            let u = ((e1 * d2.y - e2 * d1.y) / determinant).normalize();
+            let v = (e2 * d1.x - e1 * d2.x) / determinant;
+
+            for i in [a, b, c] {
+                let normal = data.normals[i];
+                let sign = normal.cross(u).dot(v).signum();
+
+                assert_near(data.tangents[i], u.extend(sign));
+            }
+        }
+    }
+}
@@ -1,7 +1,11 @@
+pub mod library;
 pub mod material;
 pub mod mesh;
+pub mod mesh_data;
 pub mod texture;
 
-pub use material::Material;
+pub use library::{Library, CUBE_MESH, WHITE_TEXTURE};
+pub use material::{Material, PbrMaterial};
 pub use mesh::Mesh;
+pub use mesh_data::MeshData;
 pub use texture::Texture;
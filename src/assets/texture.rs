@@ -4,15 +4,20 @@ use std::{
     sync::Arc,
 };
 use vulkano::{
-    device::Queue,
+    device::{Device, Queue},
     format::Format,
-    image::{view::ImageView, ImageDimensions, ImmutableImage, MipmapsCount},
+    image::{
+        attachment::AttachmentImage,
+        view::{ImageView, ImageViewAbstract},
+        ImageDimensions, ImageUsage, ImmutableImage, MipmapsCount,
+    },
     sampler::Sampler,
 };
 
 pub struct Texture {
-    pub image: Arc<ImageView<Arc<ImmutableImage>>>,
+    pub image: Arc<dyn ImageViewAbstract>,
     pub sampler: Arc<Sampler>,
+    pub target: Option<Arc<ImageView<Arc<AttachmentImage>>>>,
 }
 
 impl Texture {
@@ -33,25 +38,77 @@ impl Texture {
         let decoder = png::Decoder::new(cursor);
         let mut reader = decoder.read_info()?;
         let info = reader.info();
-        let dimensions = ImageDimensions::Dim2d {
-            width: info.width,
-            height: info.height,
-            array_layers: 1,
-        };
+        let [width, height] = [info.width, info.height];
         let mut image_data = Vec::new();
 
-        image_data.resize((info.width * info.height * 4) as usize, 0);
+        image_data.resize((width * height * 4) as usize, 0);
         reader.next_frame(&mut image_data)?;
 
+        Self::from_rgba(&image_data, [width, height], queue, sampler, format)
+    }
+
+    pub fn from_rgba(
+        data: &[u8],
+        dimensions: [u32; 2],
+        queue: Arc<Queue>,
+        sampler: Arc<Sampler>,
+        format: Format,
+    ) -> Result<Arc<Self>, Error> {
         let (image, _) = ImmutableImage::from_iter(
-            image_data.iter().cloned(),
-            dimensions,
+            data.iter().cloned(),
+            ImageDimensions::Dim2d {
+                width: dimensions[0],
+                height: dimensions[1],
+                array_layers: 1,
+            },
             MipmapsCount::Log2,
             format,
             queue,
         )?;
         let image = ImageView::new(image)?;
 
-        Ok(Arc::new(Self { image, sampler }))
+        Ok(Arc::new(Self {
+            image,
+            sampler,
+            target: None,
+        }))
+    }
+
+    pub fn solid(
+        color: [u8; 4],
+        queue: Arc<Queue>,
+        sampler: Arc<Sampler>,
+        format: Format,
+    ) -> Result<Arc<Self>, Error> {
+        Self::from_rgba(&color, [1, 1], queue, sampler, format)
+    }
+
+    pub fn dimensions(&self) -> [u32; 2] {
+        self.image.image().dimensions().width_height()
+    }
+
+    pub fn target(
+        device: Arc<Device>,
+        dimensions: [u32; 2],
+        format: Format,
+        sampler: Arc<Sampler>,
+    ) -> Result<Arc<Self>, Error> {
+        let target = ImageView::new(AttachmentImage::with_usage(
+            device,
+            dimensions,
+            format,
+            ImageUsage {
+                color_attachment: true,
+                sampled: true,
+                transfer_source: true,
+                ..ImageUsage::none()
+            },
+        )?)?;
+
+        Ok(Arc::new(Self {
+            image: target.clone(),
+            sampler,
+            target: Some(target),
+        }))
     }
 }
@@ -0,0 +1,410 @@
+use cgmath::{InnerSpace, Matrix4, Vector3, Vector4};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Aabb {
+    pub min: Vector3<f32>,
+    pub max: Vector3<f32>,
+}
+
+impl Aabb {
+    pub fn new(min: Vector3<f32>, max: Vector3<f32>) -> Self {
+        Self { min, max }
+    }
+
+    pub fn from_points<I>(points: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = Vector3<f32>>,
+    {
+        points.into_iter().fold(None, |aabb, point| {
+            Some(match aabb {
+                Some(Self { min, max }) => Self::new(
+                    Vector3::new(min.x.min(point.x), min.y.min(point.y), min.z.min(point.z)),
+                    Vector3::new(max.x.max(point.x), max.y.max(point.y), max.z.max(point.z)),
+                ),
+                None => Self::new(point, point),
+            })
+        })
+    }
+
+    pub fn center(&self) -> Vector3<f32> {
+        (self.min + self.max) / 2.0
+    }
+
+    pub fn extents(&self) -> Vector3<f32> {
+        (self.max - self.min) / 2.0
+    }
+
+    pub fn corners(&self) -> [Vector3<f32>; 8] {
+        let (min, max) = (self.min, self.max);
+
+        [
+            Vector3::new(min.x, min.y, min.z),
+            Vector3::new(max.x, min.y, min.z),
+            Vector3::new(min.x, max.y, min.z),
+            Vector3::new(max.x, max.y, min.z),
+            Vector3::new(min.x, min.y, max.z),
+            Vector3::new(max.x, min.y, max.z),
+            Vector3::new(min.x, max.y, max.z),
+            Vector3::new(max.x, max.y, max.z),
+        ]
+    }
+
+    pub fn transform(&self, matrix: &Matrix4<f32>) -> Self {
+        Self::from_points(
+            self.corners()
+                .iter()
+                .map(|corner| (matrix * corner.extend(1.0)).truncate()),
+        )
+        .unwrap_or(*self)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Sphere {
+    pub center: Vector3<f32>,
+    pub radius: f32,
+}
+
+impl Sphere {
+    pub fn new(center: Vector3<f32>, radius: f32) -> Self {
+        Self { center, radius }
+    }
+
+    pub fn from_points<I>(points: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = Vector3<f32>>,
+        I::IntoIter: Clone,
+    {
+        let points = points.into_iter();
+        let center = Aabb::from_points(points.clone())?.center();
+        let radius = points
+            .map(|point| (point - center).magnitude())
+            .fold(0.0, f32::max);
+
+        Some(Self::new(center, radius))
+    }
+
+    pub fn transform(&self, matrix: &Matrix4<f32>) -> Self {
+        let scale = [matrix.x, matrix.y, matrix.z]
+            .iter()
+            .map(|axis| axis.truncate().magnitude())
+            .fold(0.0, f32::max);
+
+        Self::new(
+            (matrix * self.center.extend(1.0)).truncate(),
+            self.radius * scale,
+        )
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Plane {
+    pub normal: Vector3<f32>,
+    pub distance: f32,
+}
+
+impl Plane {
+    pub fn from_vector(vector: Vector4<f32>) -> Option<Self> {
+        let length = vector.truncate().magnitude();
+
+        if length > f32::EPSILON {
+            Some(Self {
+                normal: vector.truncate() / length,
+                distance: vector.w / length,
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn signed_distance(&self, point: Vector3<f32>) -> f32 {
+        self.normal.dot(point) + self.distance
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frustum {
+    pub planes: Vec<Plane>,
+}
+
+impl Frustum {
+    pub fn from_matrix(matrix: &Matrix4<f32>) -> Self {
+        let row = |i: usize| Vector4::new(matrix.x[i], matrix.y[i], matrix.z[i], matrix.w[i]);
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+        let planes = [w + x, w - x, w + y, w - y, z, w - z]
+            .iter()
+            .filter_map(|vector| Plane::from_vector(*vector))
+            .collect();
+
+        Self { planes }
+    }
+
+    pub fn intersects_sphere(&self, sphere: &Sphere) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.signed_distance(sphere.center) >= -sphere.radius)
+    }
+
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            let positive = Vector3::new(
+                if plane.normal.x >= 0.0 {
+                    aabb.max.x
+                } else {
+                    aabb.min.x
+                },
+                if plane.normal.y >= 0.0 {
+                    aabb.max.y
+                } else {
+                    aabb.min.y
+                },
+                if plane.normal.z >= 0.0 {
+                    aabb.max.z
+                } else {
+                    aabb.min.z
+                },
+            );
+
+            plane.signed_distance(positive) >= 0.0
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Ray {
+    pub origin: Vector3<f32>,
+    pub direction: Vector3<f32>,
+}
+
+impl Ray {
+    pub fn new(origin: Vector3<f32>, direction: Vector3<f32>) -> Self {
+        Self {
+            origin,
+            direction: direction.normalize(),
+        }
+    }
+
+    pub fn at(&self, distance: f32) -> Vector3<f32> {
+        self.origin + self.direction * distance
+    }
+
+    pub fn transform(&self, matrix: &Matrix4<f32>) -> Self {
+        Self {
+            origin: (matrix * self.origin.extend(1.0)).truncate(),
+            direction: (matrix * self.direction.extend(0.0)).truncate(),
+        }
+    }
+
+    pub fn intersect_sphere(&self, sphere: &Sphere) -> Option<f32> {
+        let offset = self.origin - sphere.center;
+        let a = self.direction.dot(self.direction);
+        let b = offset.dot(self.direction);
+        let c = offset.dot(offset) - sphere.radius * sphere.radius;
+        let discriminant = b * b - a * c;
+
+        if discriminant < 0.0 {
+            return None;
+        }
+
+        let root = discriminant.sqrt();
+        let near = (-b - root) / a;
+        let far = (-b + root) / a;
+
+        if far < 0.0 {
+            None
+        } else {
+            Some(near.max(0.0))
+        }
+    }
+
+    pub fn intersect_aabb(&self, aabb: &Aabb) -> Option<f32> {
+        let mut near = f32::NEG_INFINITY;
+        let mut far = f32::INFINITY;
+
+        for axis in 0..3 {
+            let inverse = 1.0 / self.direction[axis];
+            let a = (aabb.min[axis] - self.origin[axis]) * inverse;
+            let b = (aabb.max[axis] - self.origin[axis]) * inverse;
+
+            near = near.max(a.min(b));
+            far = far.min(a.max(b));
+        }
+
+        if far >= near.max(0.0) {
+            Some(near.max(0.0))
+        } else {
+            None
+        }
+    }
+
+    pub fn intersect_triangle(
+        &self,
+        a: Vector3<f32>,
+        b: Vector3<f32>,
+        c: Vector3<f32>,
+    ) -> Option<(f32, Vector3<f32>)> {
+        let edge1 = b - a;
+        let edge2 = c - a;
+        let p = self.direction.cross(edge2);
+        let determinant = edge1.dot(p);
+
+        // Parallel rays are judged relative to the triangle's size, so tiny triangles still hit.
+        let scale = edge1.magnitude() * edge2.magnitude() * self.direction.magnitude();
+
+        if determinant.abs() <= f32::EPSILON * scale {
+            return None;
+        }
+
+        let inverse = 1.0 / determinant;
+        let t = self.origin - a;
+        let u = t.dot(p) * inverse;
+
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let q = t.cross(edge1);
+        let v = self.direction.dot(q) * inverse;
+
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let distance = edge2.dot(q) * inverse;
+
+        if distance < 0.0 {
+            None
+        } else {
+            Some((distance, Vector3::new(1.0 - u - v, u, v)))
+        }
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct CullStats {
+    pub drawn: usize,
+    pub culled: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::Projection;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn triangle(scale: f32) -> [Vector3<f32>; 3] {
+        [
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(scale, 0.0, 0.0),
+            Vector3::new(0.0, scale, 0.0),
+        ]
+    }
+
+    #[test]
+    fn rays_hit_triangles_with_barycentrics() {
+        let [a, b, c] = triangle(1.0);
+        let ray = Ray::new(Vector3::new(0.25, 0.5, 2.0), -Vector3::unit_z());
+        let (distance, barycentric) = ray.intersect_triangle(a, b, c).unwrap();
+
+        assert!((distance - 2.0).abs() < 1e-6);
+        assert!((barycentric - Vector3::new(0.25, 0.25, 0.5)).magnitude() < 1e-6);
+        assert!(
+            (ray.at(distance) - (a * barycentric.x + b * barycentric.y + c * barycentric.z))
+                .magnitude()
+                < 1e-6
+        );
+    }
+
+    #[test]
+    fn rays_miss_triangles_outside_behind_or_alongside() {
+        let [a, b, c] = triangle(1.0);
+
+        assert!(Ray::new(Vector3::new(0.75, 0.75, 2.0), -Vector3::unit_z())
+            .intersect_triangle(a, b, c)
+            .is_none());
+        assert!(Ray::new(Vector3::new(0.25, 0.25, 2.0), Vector3::unit_z())
+            .intersect_triangle(a, b, c)
+            .is_none());
+        assert!(Ray::new(Vector3::new(-1.0, 0.25, 0.0), Vector3::unit_x())
+            .intersect_triangle(a, b, c)
+            .is_none());
+    }
+
+    #[test]
+    fn tiny_triangles_are_still_hit() {
+        let [a, b, c] = triangle(1e-4);
+        let ray = Ray::new(Vector3::new(2e-5, 2e-5, 1.0), -Vector3::unit_z());
+        let (distance, _) = ray.intersect_triangle(a, b, c).unwrap();
+
+        assert!((distance - 1.0).abs() < 1e-6);
+    }
+
+    fn frusta() -> [Frustum; 2] {
+        [
+            Projection::Perspective {
+                fov: FRAC_PI_2,
+                near: 0.1,
+                far: 100.0,
+            },
+            Projection::ReverseZ {
+                fov: FRAC_PI_2,
+                near: 0.1,
+            },
+        ]
+        .map(|projection| Frustum::from_matrix(&projection.matrix(1.0)))
+    }
+
+    fn cube(center: Vector3<f32>, radius: f32) -> Aabb {
+        let extent = Vector3::new(radius, radius, radius);
+
+        Aabb::new(center - extent, center + extent)
+    }
+
+    fn assert_visibility(frustum: &Frustum, center: Vector3<f32>, radius: f32, visible: bool) {
+        assert_eq!(
+            frustum.intersects_sphere(&Sphere::new(center, radius)),
+            visible,
+            "sphere at {:?}",
+            center
+        );
+        assert_eq!(
+            frustum.intersects_aabb(&cube(center, radius)),
+            visible,
+            "aabb at {:?}",
+            center
+        );
+    }
+
+    #[test]
+    fn frusta_keep_what_is_inside_or_straddling() {
+        for frustum in frusta() {
+            assert_visibility(&frustum, Vector3::new(0.0, 0.0, -10.0), 1.0, true);
+            assert_visibility(&frustum, Vector3::new(0.0, 9.0, -10.0), 0.5, true);
+
+            // Straddling the right and the near plane.
+            assert_visibility(&frustum, Vector3::new(10.5, 0.0, -10.0), 1.0, true);
+            assert_visibility(&frustum, Vector3::new(0.0, 0.0, 0.0), 0.5, true);
+        }
+    }
+
+    #[test]
+    fn frusta_cull_what_is_outside() {
+        for frustum in frusta() {
+            assert_visibility(&frustum, Vector3::new(0.0, 0.0, 10.0), 1.0, false);
+            assert_visibility(&frustum, Vector3::new(50.0, 0.0, -10.0), 1.0, false);
+            assert_visibility(&frustum, Vector3::new(0.0, -50.0, -10.0), 1.0, false);
+        }
+    }
+
+    #[test]
+    fn only_finite_frusta_have_a_far_plane() {
+        let [perspective, reverse_z] = frusta();
+
+        assert_eq!(perspective.planes.len(), 6);
+        assert_eq!(reverse_z.planes.len(), 5);
+
+        assert_visibility(&perspective, Vector3::new(0.0, 0.0, -100.5), 1.0, true);
+        assert_visibility(&perspective, Vector3::new(0.0, 0.0, -200.0), 1.0, false);
+        assert_visibility(&reverse_z, Vector3::new(0.0, 0.0, -1e5), 1.0, true);
+    }
+}
@@ -0,0 +1,202 @@
+use crate::error::Error;
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, RwLock,
+    },
+};
+use vulkano::format::Format;
+
+pub struct Frame {
+    pub dimensions: [u32; 2],
+    pub data: Vec<u8>,
+}
+
+impl Frame {
+    pub fn new(dimensions: [u32; 2], data: Vec<u8>) -> Self {
+        Self { dimensions, data }
+    }
+
+    pub fn from_format(dimensions: [u32; 2], mut data: Vec<u8>, format: Format) -> Self {
+        if let Format::B8G8R8A8_SRGB | Format::B8G8R8A8_UNORM = format {
+            for pixel in data.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+
+        Self::new(dimensions, data)
+    }
+
+    pub fn from_png<R>(reader: R) -> Result<Self, Error>
+    where
+        R: Read,
+    {
+        let mut decoder = png::Decoder::new(reader);
+
+        // Palettes, low bit depths and 16-bit channels all come out as 8-bit samples.
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+
+        let mut reader = decoder.read_info()?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer)?;
+        let samples = &buffer[..info.buffer_size()];
+        let data = match info.color_type {
+            png::ColorType::Rgba => samples.to_vec(),
+            png::ColorType::Rgb => samples
+                .chunks_exact(3)
+                .flat_map(|p| [p[0], p[1], p[2], 255])
+                .collect(),
+            png::ColorType::GrayscaleAlpha => samples
+                .chunks_exact(2)
+                .flat_map(|p| [p[0], p[0], p[0], p[1]])
+                .collect(),
+            png::ColorType::Grayscale => samples.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+            png::ColorType::Indexed => {
+                return Err(Error::UnsupportedFormat("indexed PNG".to_string()))
+            }
+        };
+
+        Ok(Self::new([info.width, info.height], data))
+    }
+
+    pub fn load_png<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        Self::from_png(BufReader::new(File::open(path)?))
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Option<[u8; 4]> {
+        let [width, height] = self.dimensions;
+
+        if x >= width || y >= height {
+            return None;
+        }
+
+        let i = (y as usize * width as usize + x as usize) * 4;
+
+        self.data.get(i..i + 4)?.try_into().ok()
+    }
+
+    pub fn to_png<W>(&self, writer: W) -> Result<(), Error>
+    where
+        W: Write,
+    {
+        let mut encoder = png::Encoder::new(writer, self.dimensions[0], self.dimensions[1]);
+
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header()?;
+
+        writer.write_image_data(&self.data)?;
+
+        Ok(())
+    }
+
+    pub fn save_png<P>(&self, path: P) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+    {
+        self.to_png(BufWriter::new(File::create(path)?))
+    }
+}
+
+pub struct Capture {
+    requests: RwLock<Vec<Sender<Frame>>>,
+}
+
+impl Capture {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            requests: RwLock::new(Vec::new()),
+        })
+    }
+
+    pub fn request(&self) -> Receiver<Frame> {
+        let (sender, receiver) = mpsc::channel();
+
+        self.requests.write().unwrap().push(sender);
+
+        receiver
+    }
+
+    pub fn pending(&self) -> bool {
+        !self.requests.read().unwrap().is_empty()
+    }
+
+    pub(crate) fn fulfill(&self, dimensions: [u32; 2], data: &[u8], format: Format) {
+        let requests = std::mem::take(&mut *self.requests.write().unwrap());
+
+        for sender in requests {
+            let _ = sender.send(Frame::from_format(dimensions, data.to_vec(), format));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(color: png::ColorType, depth: png::BitDepth, samples: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, 2, 1);
+
+        encoder.set_color(color);
+        encoder.set_depth(depth);
+        encoder
+            .write_header()
+            .unwrap()
+            .write_image_data(samples)
+            .unwrap();
+
+        bytes
+    }
+
+    #[test]
+    fn rgb_pngs_gain_an_opaque_alpha() {
+        let bytes = encode(
+            png::ColorType::Rgb,
+            png::BitDepth::Eight,
+            &[1, 2, 3, 4, 5, 6],
+        );
+        let frame = Frame::from_png(&bytes[..]).unwrap();
+
+        assert_eq!(frame.data, vec![1, 2, 3, 255, 4, 5, 6, 255]);
+    }
+
+    #[test]
+    fn sixteen_bit_pngs_are_reduced_to_eight_bits() {
+        let bytes = encode(
+            png::ColorType::Rgba,
+            png::BitDepth::Sixteen,
+            &[
+                0xff, 0xff, 0x80, 0x00, 0x00, 0x00, 0xff, 0xff, 0x12, 0x34, 0, 0, 0, 0, 0xff, 0xff,
+            ],
+        );
+        let frame = Frame::from_png(&bytes[..]).unwrap();
+
+        assert_eq!(frame.data, vec![0xff, 0x80, 0x00, 0xff, 0x12, 0, 0, 0xff]);
+    }
+
+    #[test]
+    fn grayscale_pngs_are_expanded() {
+        let bytes = encode(png::ColorType::Grayscale, png::BitDepth::Eight, &[7, 9]);
+        let frame = Frame::from_png(&bytes[..]).unwrap();
+
+        assert_eq!(frame.data, vec![7, 7, 7, 255, 9, 9, 9, 255]);
+    }
+
+    #[test]
+    fn pixels_outside_the_frame_are_none() {
+        let frame = Frame::new([2, 1], vec![1, 2, 3, 4, 5, 6, 7, 8]);
+
+        assert_eq!(frame.pixel(1, 0), Some([5, 6, 7, 8]));
+        assert_eq!(frame.pixel(2, 0), None);
+        assert_eq!(frame.pixel(0, 1), None);
+        assert_eq!(Frame::new([2, 1], vec![0; 4]).pixel(1, 0), None);
+    }
+}
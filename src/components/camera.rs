@@ -1,16 +1,157 @@
-use crate::ecs::{self, reexports::*};
+use crate::{
+    assets::Texture,
+    bounds::Ray,
+    components::Transform,
+    ecs::{self, reexports::*},
+};
+use cgmath::{Matrix4, Rad, SquareMatrix, Vector4};
+use serde::{Deserialize, Serialize};
 
 pub const CAMERA_ID: &str = "camera";
 
+#[rustfmt::skip]
+pub const VULKAN_CLIP: Matrix4<f32> = Matrix4::new(
+    1.0, 0.0, 0.0, 0.0,
+    0.0, -1.0, 0.0, 0.0,
+    0.0, 0.0, 0.5, 0.0,
+    0.0, 0.0, 0.5, 1.0,
+);
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Projection {
+    Perspective { fov: f32, near: f32, far: f32 },
+    Orthographic { size: f32, near: f32, far: f32 },
+    ReverseZ { fov: f32, near: f32 },
+    Custom(Matrix4<f32>),
+}
+
+impl Projection {
+    pub fn matrix(&self, aspect_ratio: f32) -> Matrix4<f32> {
+        match *self {
+            Self::Perspective { fov, near, far } => {
+                VULKAN_CLIP * cgmath::perspective(Rad(fov), aspect_ratio, near, far)
+            }
+
+            Self::Orthographic { size, near, far } => {
+                VULKAN_CLIP
+                    * cgmath::ortho(
+                        -size * aspect_ratio,
+                        size * aspect_ratio,
+                        -size,
+                        size,
+                        near,
+                        far,
+                    )
+            }
+
+            Self::ReverseZ { fov, near } => {
+                let f = 1.0 / (fov / 2.0).tan();
+
+                #[rustfmt::skip]
+                let matrix = Matrix4::new(
+                    f / aspect_ratio, 0.0, 0.0, 0.0,
+                    0.0, -f, 0.0, 0.0,
+                    0.0, 0.0, 0.0, -1.0,
+                    0.0, 0.0, near, 0.0,
+                );
+
+                matrix
+            }
+
+            Self::Custom(matrix) => matrix,
+        }
+    }
+
+    pub fn near(&self) -> Option<f32> {
+        match *self {
+            Self::Perspective { near, .. }
+            | Self::Orthographic { near, .. }
+            | Self::ReverseZ { near, .. } => Some(near),
+            Self::Custom(_) => None,
+        }
+    }
+
+    pub fn far(&self) -> Option<f32> {
+        match *self {
+            Self::Perspective { far, .. } | Self::Orthographic { far, .. } => Some(far),
+            Self::ReverseZ { .. } | Self::Custom(_) => None,
+        }
+    }
+
+    pub fn reverse_z(&self) -> bool {
+        matches!(self, Self::ReverseZ { .. })
+    }
+
+    pub fn clear_depth(&self) -> f32 {
+        if self.reverse_z() {
+            0.0
+        } else {
+            1.0
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn full() -> Self {
+        Self::new(0.0, 0.0, 1.0, 1.0)
+    }
+
+    pub fn pixels(&self, dimensions: &[u32; 2]) -> ([f32; 2], [f32; 2]) {
+        let [width, height] = [dimensions[0] as f32, dimensions[1] as f32];
+
+        (
+            [self.x * width, self.y * height],
+            [self.width * width, self.height * height],
+        )
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Clear {
+    Background,
+    Color(Vector4<f32>),
+    DepthOnly,
+    Nothing,
+}
+
+#[derive(Reflect)]
 pub struct CameraData {
-    pub fov: f32,
-    pub near: f32,
-    pub far: f32,
+    pub projection: Projection,
+    pub viewport: Rect,
+    pub priority: i32,
+    pub clear: Clear,
+    pub active: bool,
+    #[reflect(skip)]
+    pub target: Option<Arc<Texture>>,
 }
 
 impl CameraData {
-    pub fn new(fov: f32, near: f32, far: f32) -> Self {
-        Self { fov, near, far }
+    pub fn new(projection: Projection) -> Self {
+        Self {
+            projection,
+            viewport: Rect::full(),
+            priority: 0,
+            clear: Clear::Background,
+            active: true,
+            target: None,
+        }
     }
 }
 
@@ -19,16 +160,56 @@ pub struct Camera {
     pub id: Arc<String>,
     pub tid: Arc<String>,
     pub entity: Arc<RwLock<Option<Arc<Entity>>>>,
+    #[reflect]
     pub data: RwLock<CameraData>,
 }
 
 impl Camera {
     pub fn new(id: Arc<String>, fov: f32, near: f32, far: f32) -> Arc<Self> {
+        Self::with_projection(id, Projection::Perspective { fov, near, far })
+    }
+
+    pub fn with_projection(id: Arc<String>, projection: Projection) -> Arc<Self> {
         Arc::new(Self {
             id,
             tid: ecs::id(CAMERA_ID),
             entity: ecs::entity(None),
-            data: RwLock::new(CameraData::new(fov, near, far)),
+            data: RwLock::new(CameraData::new(projection)),
         })
     }
+
+    pub fn projection(&self, aspect_ratio: f32) -> Matrix4<f32> {
+        self.data.read().unwrap().projection.matrix(aspect_ratio)
+    }
+
+    pub fn screen_point_to_ray(&self, point: [f32; 2], dimensions: &[u32; 2]) -> Option<Ray> {
+        let (viewport, projection) = {
+            let data = self.data.read().unwrap();
+
+            (data.viewport, data.projection)
+        };
+        let (origin, size) = viewport.pixels(dimensions);
+        let inverse = (projection.matrix(size[0] / size[1]) * self.view()).invert()?;
+        let x = (point[0] - origin[0]) / size[0] * 2.0 - 1.0;
+        let y = (point[1] - origin[1]) / size[1] * 2.0 - 1.0;
+        let near = if projection.reverse_z() { 1.0 } else { 0.0 };
+        let unproject = |z: f32| {
+            let point = inverse * Vector4::new(x, y, z, 1.0);
+
+            point.truncate() / point.w
+        };
+        let start = unproject(near);
+        let end = unproject(0.5);
+
+        Some(Ray::new(start, end - start))
+    }
+
+    pub fn view(&self) -> Matrix4<f32> {
+        let entity = { self.entity.read().unwrap().clone() };
+
+        entity
+            .and_then(|entity| entity.first_of::<Transform>())
+            .and_then(|transform| transform.world_matrix().invert())
+            .unwrap_or_else(Matrix4::identity)
+    }
 }
@@ -1,33 +1,86 @@
-use crate::ecs::{self, reexports::*};
-use cgmath::Vector3;
+use crate::{
+    components::{camera::VULKAN_CLIP, Transform},
+    ecs::{self, reexports::*},
+    error::Error,
+    shaders::fragment,
+};
+use cgmath::{Matrix4, Rad, SquareMatrix, Vector3};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
 
 pub const LIGHT_ID: &str = "light";
+pub const SHADOW_NEAR: f32 = 0.1;
 
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum LightType {
+    Directional,
+    Point {
+        range: f32,
+    },
+    Spot {
+        range: f32,
+        inner_angle: f32,
+        outer_angle: f32,
+    },
+}
+
+impl LightType {
+    pub fn kind(&self) -> u32 {
+        match self {
+            Self::Directional => 0,
+            Self::Point { .. } => 1,
+            Self::Spot { .. } => 2,
+        }
+    }
+
+    pub fn range(&self) -> Option<f32> {
+        match *self {
+            Self::Directional => None,
+            Self::Point { range } | Self::Spot { range, .. } => Some(range),
+        }
+    }
+
+    // Point lights would need a cube map per light, which the shadow atlas doesn't have.
+    pub fn supports_shadows(&self) -> bool {
+        !matches!(self, Self::Point { .. })
+    }
+
+    pub fn cutoffs(&self) -> (f32, f32) {
+        match *self {
+            Self::Spot {
+                inner_angle,
+                outer_angle,
+                ..
+            } => (inner_angle.cos(), outer_angle.cos()),
+            _ => (-1.0, -1.0),
+        }
+    }
+}
+
+#[derive(Reflect)]
 pub struct LightData {
+    pub light_type: LightType,
     pub color: Vector3<f32>,
     pub intensity: f32,
-    pub cutoff: f32,
-    pub outer_cutoff: f32,
-    pub attenuation: f32,
-    pub directional: bool,
+    // Ignored for light types without `supports_shadows`; use `Light::set_shadows` to be told.
+    pub shadows: bool,
+    pub bias: f32,
+    pub normal_bias: f32,
+    pub shadow_strength: f32,
+    pub shadow_range: f32,
 }
 
 impl LightData {
-    pub fn new(
-        color: Vector3<f32>,
-        intensity: f32,
-        cutoff: f32,
-        outer_cutoff: f32,
-        attenuation: f32,
-        directional: bool,
-    ) -> Self {
+    pub fn new(light_type: LightType, color: Vector3<f32>, intensity: f32) -> Self {
         Self {
+            light_type,
             color,
             intensity,
-            cutoff,
-            outer_cutoff,
-            attenuation,
-            directional,
+            shadows: false,
+            bias: 0.005,
+            normal_bias: 0.02,
+            shadow_strength: 1.0,
+            shadow_range: 50.0,
         }
     }
 }
@@ -37,31 +90,142 @@ pub struct Light {
     pub id: Arc<String>,
     pub tid: Arc<String>,
     pub entity: Arc<RwLock<Option<Arc<Entity>>>>,
+    #[reflect]
     pub data: RwLock<LightData>,
 }
 
 impl Light {
     pub fn new(
         id: Arc<String>,
+        light_type: LightType,
         color: Vector3<f32>,
         intensity: f32,
-        cutoff: f32,
-        outer_cutoff: f32,
-        attenuation: f32,
-        directional: bool,
     ) -> Arc<Self> {
         Arc::new(Self {
             id,
             tid: ecs::id(LIGHT_ID),
             entity: ecs::entity(None),
-            data: RwLock::new(LightData::new(
-                color,
-                intensity,
-                cutoff,
-                outer_cutoff,
-                attenuation,
-                directional,
-            )),
+            data: RwLock::new(LightData::new(light_type, color, intensity)),
         })
     }
+
+    pub fn directional(id: Arc<String>, color: Vector3<f32>, intensity: f32) -> Arc<Self> {
+        Self::new(id, LightType::Directional, color, intensity)
+    }
+
+    pub fn point(id: Arc<String>, color: Vector3<f32>, intensity: f32, range: f32) -> Arc<Self> {
+        Self::new(id, LightType::Point { range }, color, intensity)
+    }
+
+    pub fn spot(
+        id: Arc<String>,
+        color: Vector3<f32>,
+        intensity: f32,
+        range: f32,
+        inner_angle: f32,
+        outer_angle: f32,
+    ) -> Arc<Self> {
+        Self::new(
+            id,
+            LightType::Spot {
+                range,
+                inner_angle,
+                outer_angle,
+            },
+            color,
+            intensity,
+        )
+    }
+
+    pub fn set_shadows(&self, enabled: bool) -> Result<(), Error> {
+        let mut data = self.data.write().unwrap();
+
+        if enabled && !data.light_type.supports_shadows() {
+            return Err(Error::UnsupportedShadows(data.light_type));
+        }
+
+        data.shadows = enabled;
+
+        Ok(())
+    }
+
+    pub fn transform(&self) -> Option<Arc<Transform>> {
+        let entity = { self.entity.read().unwrap().clone() }?;
+
+        entity.first_of::<Transform>()
+    }
+
+    pub fn shadow_matrix(&self) -> Option<Matrix4<f32>> {
+        let transform = self.transform()?;
+        let view = (Matrix4::from_translation(transform.world_position())
+            * Matrix4::from(transform.world_rotation()))
+        .invert()?;
+        let data = self.data.read().unwrap();
+        let proj = match data.light_type {
+            LightType::Directional => {
+                let range = data.shadow_range;
+
+                cgmath::ortho(-range, range, -range, range, -range, range)
+            }
+
+            LightType::Spot {
+                range, outer_angle, ..
+            } => {
+                let fov = (outer_angle * 2.0).clamp(0.01, PI * 0.9);
+
+                cgmath::perspective(Rad(fov), 1.0, SHADOW_NEAR, range)
+            }
+
+            LightType::Point { .. } => return None,
+        };
+
+        Some(VULKAN_CLIP * proj * view)
+    }
+
+    pub fn uniform(&self, shadow: Option<u32>) -> Option<fragment::ty::Light> {
+        let transform = self.transform()?;
+        let proj = shadow
+            .and_then(|_| self.shadow_matrix())
+            .unwrap_or_else(Matrix4::identity);
+        let data = self.data.read().unwrap();
+        let (inner_cutoff, outer_cutoff) = data.light_type.cutoffs();
+
+        Some(fragment::ty::Light {
+            proj: proj.into(),
+            position: transform.world_position().into(),
+            kind: data.light_type.kind(),
+            direction: transform.forward().into(),
+            range: data.light_type.range().unwrap_or(0.0),
+            color: data.color.into(),
+            intensity: data.intensity,
+            inner_cutoff,
+            outer_cutoff,
+            shadow: shadow.map(|index| index as i32).unwrap_or(-1),
+            bias: data.bias,
+            normal_bias: data.normal_bias,
+            shadow_strength: data.shadow_strength,
+            _dummy0: [0; 8],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn point_lights_refuse_shadows() {
+        let color = Vector3::new(1.0, 1.0, 1.0);
+        let point = Light::point(ecs::id("point"), color, 1.0, 10.0);
+        let spot = Light::spot(ecs::id("spot"), color, 1.0, 10.0, 0.2, 0.4);
+
+        assert!(matches!(
+            point.set_shadows(true),
+            Err(Error::UnsupportedShadows(LightType::Point { .. }))
+        ));
+        assert!(!point.data.read().unwrap().shadows);
+        assert!(point.set_shadows(false).is_ok());
+        assert!(spot.set_shadows(true).is_ok());
+        assert!(spot.data.read().unwrap().shadows);
+    }
 }
@@ -2,10 +2,54 @@ pub mod camera;
 pub mod event_handler;
 pub mod light;
 pub mod model;
+pub mod prefab_instance;
 pub mod transform;
 
-pub use camera::{Camera, CameraData, CAMERA_ID};
+pub use camera::{Camera, CameraData, Clear, Projection, Rect, CAMERA_ID};
 pub use event_handler::{EventHandler, EVENT_HANDLER_ID};
-pub use light::{Light, LightData, LIGHT_ID};
+pub use light::{Light, LightData, LightType, LIGHT_ID};
 pub use model::{Model, ModelData, MODEL_ID};
-pub use transform::{Transform, TransformData, TRANSFORM_ID};
+pub use prefab_instance::{PrefabInstance, PrefabInstanceData, PREFAB_INSTANCE_ID};
+pub use transform::{
+    Transform, TransformData, TransformPropagation, TRANSFORM_ID, TRANSFORM_PROPAGATION_ID,
+};
+
+use crate::{
+    assets::{Library, Material, CUBE_MESH, WHITE_TEXTURE},
+    ecs::Registry,
+    error::Error,
+};
+use cgmath::{One, Quaternion, Vector3, Vector4, Zero};
+use std::f32::consts::FRAC_PI_4;
+
+// Models start out as a white unit cube, since a constructor has no way to pick assets.
+pub fn register(registry: &Registry, library: &Library) -> Result<(), Error> {
+    let mesh = library.mesh(CUBE_MESH)?;
+    let texture = library.texture(WHITE_TEXTURE)?;
+
+    registry.register(TRANSFORM_ID, |id| {
+        Transform::new(
+            id,
+            Vector3::zero(),
+            Quaternion::one(),
+            Vector3::new(1.0, 1.0, 1.0),
+        )
+    });
+    registry.register(CAMERA_ID, |id| Camera::new(id, FRAC_PI_4, 0.1, 100.0));
+    registry.register(LIGHT_ID, |id| {
+        Light::point(id, Vector3::new(1.0, 1.0, 1.0), 1.0, 10.0)
+    });
+    registry.register(MODEL_ID, move |id| {
+        Model::new(
+            id,
+            mesh.clone(),
+            texture.clone(),
+            Material::new(0.2, 0.8, 0.5, 32),
+            Vector4::new(1.0, 1.0, 1.0, 1.0),
+            true,
+            true,
+        )
+    });
+
+    Ok(())
+}
@@ -1,14 +1,18 @@
 use crate::{
-    assets::{Material, Mesh, Texture},
-    components::{Camera, Light, Transform, TRANSFORM_ID},
+    assets::{Material, Mesh, PbrMaterial, Texture},
+    bounds::{Aabb, Sphere},
+    components::{Camera, Light, Transform},
     ecs::{self, reexports::*, Component, Entity},
     engine::InitializedEngine,
     shaders::{
-        fragment::{self, MAX_LIGHTS},
+        fragment::{
+            self, EMISSIVE_TEXTURE, MAX_LIGHTS, METALLIC_TEXTURE, NORMAL_TEXTURE,
+            OCCLUSION_TEXTURE, ROUGHNESS_TEXTURE,
+        },
         vertex,
     },
 };
-use cgmath::{EuclideanSpace, Matrix4, Point3, Rad, Vector3, Vector4, Zero};
+use cgmath::{Matrix, Matrix4, SquareMatrix, Vector4};
 use vulkano::{
     buffer::TypedBufferAccess,
     command_buffer::PrimaryAutoCommandBuffer,
@@ -19,13 +23,19 @@ use vulkano::{
 
 pub const MODEL_ID: &str = "model";
 
+#[derive(Reflect)]
 pub struct ModelData {
+    #[reflect(skip)]
     pub mesh: Arc<Mesh>,
+    #[reflect(skip)]
     pub texture: Arc<Texture>,
+    #[reflect(skip)]
     pub material: Arc<Material>,
     pub color: Vector4<f32>,
     pub visible: bool,
     pub lit: bool,
+    pub casts_shadows: bool,
+    pub receives_shadows: bool,
 }
 
 impl ModelData {
@@ -44,6 +54,8 @@ impl ModelData {
             color,
             visible,
             lit,
+            casts_shadows: true,
+            receives_shadows: true,
         }
     }
 }
@@ -53,6 +65,7 @@ pub struct Model {
     pub id: Arc<String>,
     pub tid: Arc<String>,
     pub entity: Arc<RwLock<Option<Arc<Entity>>>>,
+    #[reflect]
     pub data: RwLock<ModelData>,
 }
 
@@ -74,6 +87,41 @@ impl Model {
         })
     }
 
+    pub fn samples(&self, texture: &Arc<Texture>) -> bool {
+        let data = self.data.read().unwrap();
+        let material = &data.material;
+        let mut textures = vec![&data.texture];
+
+        textures.extend(&material.normal_texture);
+
+        if let Some(pbr) = &material.pbr {
+            textures.extend(
+                [
+                    &pbr.base_color_texture,
+                    &pbr.metallic_texture,
+                    &pbr.roughness_texture,
+                    &pbr.occlusion_texture,
+                    &pbr.emissive_texture,
+                ]
+                .into_iter()
+                .flatten(),
+            );
+        }
+
+        textures.into_iter().any(|t| Arc::ptr_eq(t, texture))
+    }
+
+    pub fn world_bounds(&self) -> Option<(Aabb, Sphere)> {
+        let entity = { self.entity.read().unwrap().clone() }?;
+        let matrix = entity.first_of::<Transform>()?.world_matrix();
+        let data = self.data.read().unwrap();
+
+        Some((
+            data.mesh.aabb.transform(&matrix),
+            data.mesh.sphere.transform(&matrix),
+        ))
+    }
+
     pub fn draw(
         &self,
         initialized_engine: &mut InitializedEngine,
@@ -93,45 +141,21 @@ impl Model {
 
         if let (Some(entity), Some(camera_entity)) = (entity, camera_entity) {
             if let (Some(transform), Some(camera_transform)) = (
-                entity.get_first::<Transform>(ecs::id(TRANSFORM_ID)),
-                camera_entity.get_first::<Transform>(ecs::id(TRANSFORM_ID)),
+                entity.first_of::<Transform>(),
+                camera_entity.first_of::<Transform>(),
             ) {
-                let transform_data = transform.calculate();
-                let camera_transform_data = camera_transform.calculate();
                 let uniform_buffer_subbuffer = {
-                    let rotation = Matrix4::from_angle_z(Rad(transform_data.rotation.z))
-                        * Matrix4::from_angle_y(Rad(transform_data.rotation.y))
-                        * Matrix4::from_angle_x(Rad(transform_data.rotation.x));
                     let aspect_ratio = dimensions[0] as f32 / dimensions[1] as f32;
-                    let proj = {
-                        let camera_data = camera.data.read().unwrap();
-
-                        cgmath::perspective(
-                            Rad(camera_data.fov),
-                            aspect_ratio,
-                            camera_data.near,
-                            camera_data.far,
-                        )
-                    };
-                    let camera_rotation =
-                        Matrix4::from_angle_z(Rad(camera_transform_data.rotation.z))
-                            * Matrix4::from_angle_y(Rad(camera_transform_data.rotation.y))
-                            * Matrix4::from_angle_x(Rad(camera_transform_data.rotation.x));
-                    let translation = Matrix4::from_translation(transform_data.position);
-                    let camera_translation =
-                        Matrix4::from_translation(camera_transform_data.position);
-                    let scale = Matrix4::from_nonuniform_scale(
-                        transform_data.scale.x,
-                        transform_data.scale.y,
-                        transform_data.scale.z,
-                    );
+                    let model = transform.world_matrix();
+                    let normal = model
+                        .invert()
+                        .map(|m| m.transpose())
+                        .unwrap_or_else(Matrix4::identity);
                     let uniform_data = vertex::ty::Data {
-                        proj: proj.into(),
-                        scale: scale.into(),
-                        translation: translation.into(),
-                        rotation: rotation.into(),
-                        cam_rotation: camera_rotation.into(),
-                        cam_translation: camera_translation.into(),
+                        proj: camera.projection(aspect_ratio).into(),
+                        view: camera.view().into(),
+                        model: model.into(),
+                        normal: normal.into(),
                     };
 
                     Arc::new(
@@ -142,64 +166,29 @@ impl Model {
                     )
                 };
 
-                let frag_uniform_buffer_subbuffer = {
-                    let lights = {
-                        for (i, light) in lights.iter().enumerate() {
-                            let light_entity = { light.entity.read().unwrap().clone() };
+                let default_pbr = PbrMaterial::default();
+                let pbr = data.material.pbr.as_ref().unwrap_or(&default_pbr);
+                let base_color_texture = pbr
+                    .base_color_texture
+                    .clone()
+                    .unwrap_or_else(|| data.texture.clone());
+                let textures = [
+                    (METALLIC_TEXTURE, &pbr.metallic_texture),
+                    (ROUGHNESS_TEXTURE, &pbr.roughness_texture),
+                    (NORMAL_TEXTURE, &data.material.normal_texture),
+                    (OCCLUSION_TEXTURE, &pbr.occlusion_texture),
+                    (EMISSIVE_TEXTURE, &pbr.emissive_texture),
+                ];
+                let texture_flags = textures
+                    .iter()
+                    .filter(|(_, texture)| texture.is_some())
+                    .fold(0, |flags, (flag, _)| flags | flag);
 
-                            if let Some(light_entity) = light_entity {
-                                if let Some(light_transform) =
-                                    light_entity.get_first::<Transform>(ecs::id(TRANSFORM_ID))
-                                {
-                                    let light_transform_data = light_transform.calculate();
-                                    let light_translation =
-                                        Matrix4::from_translation(light_transform_data.position);
-                                    let light_rotation =
-                                        Matrix4::from_angle_z(Rad(light_transform_data.rotation.z))
-                                            * Matrix4::from_angle_y(Rad(light_transform_data
-                                                .rotation
-                                                .y))
-                                            * Matrix4::from_angle_x(Rad(light_transform_data
-                                                .rotation
-                                                .x));
-                                    let proj = {
-                                        let camera_data = camera.data.read().unwrap();
-
-                                        cgmath::ortho(
-                                            -camera_data.far,
-                                            camera_data.far,
-                                            -camera_data.far,
-                                            camera_data.far,
-                                            camera_data.near,
-                                            camera_data.far,
-                                        )
-                                    };
-                                    let look_at = Matrix4::look_at_rh(
-                                        Point3::from_vec(light_transform_data.position),
-                                        Point3::from_vec(Vector3::zero()),
-                                        Vector3::new(0.0, 1.0, 0.0),
-                                    );
-                                    let light_data = light.data.read().unwrap();
-
-                                    initialized_engine.lights_array[i] = fragment::ty::Light {
-                                        position: light_translation.into(),
-                                        rotation: light_rotation.into(),
-                                        proj: (proj * look_at).into(),
-                                        color: light_data.color.into(),
-                                        directional: light_data.directional as u32,
-                                        intensity: light_data.intensity,
-                                        cutoff: light_data.cutoff,
-                                        outer_cutoff: light_data.outer_cutoff,
-                                        attenuation: light_data.attenuation,
-                                    };
-                                }
-                            }
-                        }
-                        fragment::ty::LightArray {
-                            len: lights.len().clamp(0, MAX_LIGHTS) as u32,
-                            array: initialized_engine.lights_array,
-                            _dummy0: [0; 12],
-                        }
+                let frag_uniform_buffer_subbuffer = {
+                    let lights = fragment::ty::LightArray {
+                        len: lights.len().clamp(0, MAX_LIGHTS) as u32,
+                        array: initialized_engine.lights_array,
+                        _dummy0: [0; 12],
                     };
 
                     let uniform_data = {
@@ -210,8 +199,19 @@ impl Model {
                             diff_strength: data.material.diff_strength,
                             spec_strength: data.material.spec_strength,
                             spec_power: data.material.spec_power,
+                            receives_shadows: data.receives_shadows.into(),
+                            camera_position: camera_transform.world_position().into(),
+                            pbr: data.material.pbr.is_some().into(),
+                            base_color: pbr.base_color.into(),
+                            emissive: pbr.emissive.into(),
+                            metallic: pbr.metallic,
+                            roughness: pbr.roughness,
+                            normal_scale: data.material.normal_scale,
+                            occlusion_strength: pbr.occlusion_strength,
+                            textures: texture_flags,
                             lights,
                             _dummy0: [0; 12],
+                            _dummy1: [0; 12],
                         }
                     };
 
@@ -237,9 +237,25 @@ impl Model {
                 let mut set_builder = PersistentDescriptorSet::start(set_layout.clone());
 
                 set_builder
-                    .add_sampled_image(data.texture.image.clone(), data.texture.sampler.clone())
+                    .add_sampled_image(
+                        base_color_texture.image.clone(),
+                        base_color_texture.sampler.clone(),
+                    )
+                    .unwrap()
+                    .add_sampled_image(
+                        initialized_engine.shadow_atlas.image.clone(),
+                        initialized_engine.shadow_atlas.sampler.clone(),
+                    )
                     .unwrap();
 
+                for (_, texture) in &textures {
+                    let texture = texture.as_ref().unwrap_or(&base_color_texture);
+
+                    set_builder
+                        .add_sampled_image(texture.image.clone(), texture.sampler.clone())
+                        .unwrap();
+                }
+
                 let image_set = Arc::new(set_builder.build().unwrap());
 
                 builder
@@ -249,7 +265,14 @@ impl Model {
                         0,
                         vec![set, image_set],
                     )
-                    .bind_vertex_buffers(0, (data.mesh.vertices.clone(), data.mesh.normals.clone()))
+                    .bind_vertex_buffers(
+                        0,
+                        (
+                            data.mesh.vertices.clone(),
+                            data.mesh.normals.clone(),
+                            data.mesh.tangents.clone(),
+                        ),
+                    )
                     .bind_index_buffer(data.mesh.indices.clone())
                     .draw_indexed(data.mesh.indices.len() as u32, 1, 0, 0, 0)
                     .unwrap();
@@ -0,0 +1,186 @@
+use crate::{
+    assets::Library,
+    components::{Camera, Model},
+    ecs::{self, reexports::*, Registry, ENTITY_ID},
+    error::Error,
+    prefab::{Override, Prefab},
+};
+
+pub const PREFAB_INSTANCE_ID: &str = "prefab instance";
+
+pub struct PrefabInstanceData {
+    pub prefab: Arc<Prefab>,
+    pub prefix: String,
+    pub overrides: Vec<Override>,
+}
+
+impl PrefabInstanceData {
+    pub fn new(prefab: Arc<Prefab>, prefix: String, overrides: Vec<Override>) -> Self {
+        Self {
+            prefab,
+            prefix,
+            overrides,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct PrefabInstance {
+    pub id: Arc<String>,
+    pub tid: Arc<String>,
+    pub entity: Arc<RwLock<Option<Arc<Entity>>>>,
+    pub data: RwLock<PrefabInstanceData>,
+}
+
+impl PrefabInstance {
+    pub fn new(
+        id: Arc<String>,
+        prefab: Arc<Prefab>,
+        prefix: String,
+        overrides: Vec<Override>,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            id,
+            tid: ecs::id(PREFAB_INSTANCE_ID),
+            entity: ecs::entity(None),
+            data: RwLock::new(PrefabInstanceData::new(prefab, prefix, overrides)),
+        })
+    }
+
+    pub fn set_override(&self, value: Override) {
+        let mut data = self.data.write().unwrap();
+
+        data.overrides.retain(|o| {
+            !(o.entity == value.entity && o.component == value.component && o.field == value.field)
+        });
+        data.overrides.push(value);
+    }
+
+    // Updates the entity in place so anything added outside of the prefab, and any references
+    // to its components, survive.
+    pub fn apply(&self, library: &Library, registry: &Registry) -> Result<(), Error> {
+        let entity = { self.entity.read().unwrap().clone() };
+
+        if let Some(entity) = entity {
+            let data = self.data.read().unwrap();
+            let fresh = data
+                .prefab
+                .build(&data.prefix, &data.overrides, library, registry)?;
+
+            reconcile(&entity, &fresh, &format!("{}:", data.prefix))?;
+        }
+
+        Ok(())
+    }
+}
+
+fn reconcile(entity: &Arc<Entity>, fresh: &Arc<Entity>, prefix: &str) -> Result<(), Error> {
+    let current = { entity.components().read().unwrap().clone() };
+    let components = { fresh.components().read().unwrap().clone() };
+    let matches =
+        |a: &Arc<dyn Component>, b: &Arc<dyn Component>| a.id() == b.id() && a.tid() == b.tid();
+
+    for component in &components {
+        match current.iter().find(|c| matches(c, component)) {
+            Some(existing) if *existing.tid() == ENTITY_ID => {
+                let existing = existing.clone().as_any().downcast::<Entity>().unwrap();
+                let component = component.clone().as_any().downcast::<Entity>().unwrap();
+
+                reconcile(&existing, &component, prefix)?;
+            }
+
+            Some(existing) => update(existing, component)?,
+            None => entity.add_dyn(component.clone()),
+        }
+    }
+
+    // Only what the prefab put there is removed; the prefix tells the two apart.
+    for component in &current {
+        if component.id().starts_with(prefix) && !components.iter().any(|c| matches(c, component)) {
+            entity.remove(component);
+        }
+    }
+
+    Ok(())
+}
+
+fn update(existing: &Arc<dyn Component>, fresh: &Arc<dyn Component>) -> Result<(), Error> {
+    reflect::apply(existing.as_ref(), &reflect::values(fresh.as_ref()))?;
+
+    // Assets aren't reflected, so they're carried over by hand.
+    let (existing, fresh) = (existing.clone().as_any(), fresh.clone().as_any());
+
+    if let (Some(existing), Some(fresh)) = (
+        existing.downcast_ref::<Model>(),
+        fresh.downcast_ref::<Model>(),
+    ) {
+        let fresh = fresh.data.read().unwrap();
+        let mut data = existing.data.write().unwrap();
+
+        data.mesh = fresh.mesh.clone();
+        data.texture = fresh.texture.clone();
+        data.material = fresh.material.clone();
+    }
+
+    if let (Some(existing), Some(fresh)) = (
+        existing.downcast_ref::<Camera>(),
+        fresh.downcast_ref::<Camera>(),
+    ) {
+        existing.data.write().unwrap().target = fresh.data.read().unwrap().target.clone();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::Transform;
+    use cgmath::{Quaternion, Vector3};
+
+    fn transform(id: &str, x: f32) -> Arc<Transform> {
+        Transform::new(
+            ecs::id(id),
+            Vector3::new(x, 0.0, 0.0),
+            Quaternion::new(1.0, 0.0, 0.0, 0.0),
+            Vector3::new(1.0, 1.0, 1.0),
+        )
+    }
+
+    #[test]
+    fn reconcile_updates_in_place_and_keeps_foreign_components() {
+        let entity = Entity::new(ecs::id("p#0:root"));
+        let kept = transform("p#0:transform", 0.0);
+        let child = Entity::new(ecs::id("p#0:child"));
+        let extra = Entity::new(ecs::id("extra"));
+
+        child.add(&transform("p#0:child transform", 0.0));
+        entity.add(&kept);
+        entity.add(&child);
+        entity.add(&extra);
+        entity.add(&transform("p#0:stale", 0.0));
+
+        let fresh = Entity::new(ecs::id("p#0:root"));
+        let fresh_child = Entity::new(ecs::id("p#0:child"));
+
+        fresh_child.add(&transform("p#0:child transform", 2.0));
+        fresh.add(&transform("p#0:transform", 1.0));
+        fresh.add(&fresh_child);
+
+        reconcile(&entity, &fresh, "p#0:").unwrap();
+
+        let transforms = entity.of_type::<Transform>();
+        let children = entity.children();
+
+        assert_eq!(transforms.len(), 1);
+        assert!(Arc::ptr_eq(&transforms[0], &kept));
+        assert_eq!(kept.data().position.x, 1.0);
+        assert_eq!(children.len(), 2);
+        assert!(children.iter().any(|c| Arc::ptr_eq(c, &child)));
+        assert!(children.iter().any(|c| Arc::ptr_eq(c, &extra)));
+        assert_eq!(
+            child.first_of::<Transform>().unwrap().data().position.x,
+            2.0
+        );
+    }
+}
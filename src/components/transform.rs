@@ -1,22 +1,38 @@
-use crate::ecs::{self, reexports::*};
-use cgmath::{Vector3, Zero};
+use crate::ecs::{self, reexports::*, Access, System};
+use cgmath::{InnerSpace, Matrix3, Matrix4, One, Quaternion, Rotation, SquareMatrix, Vector3};
+use std::sync::atomic::{AtomicBool, Ordering};
 
 pub const TRANSFORM_ID: &str = "transform";
+pub const TRANSFORM_PROPAGATION_ID: &str = "transform propagation";
 
+#[derive(Reflect, Copy, Clone, PartialEq)]
 pub struct TransformData {
     pub position: Vector3<f32>,
-    pub rotation: Vector3<f32>,
+    pub rotation: Quaternion<f32>,
     pub scale: Vector3<f32>,
 }
 
 impl TransformData {
-    pub fn new(position: Vector3<f32>, rotation: Vector3<f32>, scale: Vector3<f32>) -> Self {
+    pub fn new(position: Vector3<f32>, rotation: Quaternion<f32>, scale: Vector3<f32>) -> Self {
         Self {
             position,
             rotation,
             scale,
         }
     }
+
+    pub fn matrix(&self) -> Matrix4<f32> {
+        Matrix4::from_translation(self.position)
+            * Matrix4::from(self.rotation)
+            * Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z)
+    }
+}
+
+struct TransformCache {
+    local: TransformData,
+    parent: (Matrix4<f32>, Quaternion<f32>),
+    world: Matrix4<f32>,
+    rotation: Quaternion<f32>,
 }
 
 #[derive(Component)]
@@ -24,53 +40,364 @@ pub struct Transform {
     pub id: Arc<String>,
     pub tid: Arc<String>,
     pub entity: Arc<RwLock<Option<Arc<Entity>>>>,
-    pub data: RwLock<TransformData>,
+    #[reflect(mark_dirty)]
+    data: RwLock<TransformData>,
+    cache: RwLock<TransformCache>,
+    dirty: AtomicBool,
+    dirty_descendants: AtomicBool,
 }
 
 impl Transform {
     pub fn new(
         id: Arc<String>,
         position: Vector3<f32>,
-        rotation: Vector3<f32>,
+        rotation: Quaternion<f32>,
         scale: Vector3<f32>,
     ) -> Arc<Self> {
-        let data = RwLock::new(TransformData::new(position, rotation, scale));
+        let data = TransformData::new(position, rotation, scale);
 
         Arc::new(Self {
             id,
             tid: ecs::id(TRANSFORM_ID),
             entity: ecs::entity(None),
-            data,
+            data: RwLock::new(data),
+            cache: RwLock::new(TransformCache {
+                local: data,
+                parent: (Matrix4::identity(), Quaternion::one()),
+                world: data.matrix(),
+                rotation,
+            }),
+            dirty: AtomicBool::new(true),
+            dirty_descendants: AtomicBool::new(false),
         })
     }
 
-    fn calculate_inner(&self, data: &mut TransformData) {
+    pub fn data(&self) -> TransformData {
+        *self.data.read().unwrap()
+    }
+
+    pub fn set_data(&self, data: TransformData) {
+        *self.data.write().unwrap() = data;
+        self.mark_dirty();
+    }
+
+    pub fn set_position(&self, position: Vector3<f32>) {
+        self.data.write().unwrap().position = position;
+        self.mark_dirty();
+    }
+
+    pub fn set_rotation(&self, rotation: Quaternion<f32>) {
+        self.data.write().unwrap().rotation = rotation;
+        self.mark_dirty();
+    }
+
+    pub fn set_scale(&self, scale: Vector3<f32>) {
+        self.data.write().unwrap().scale = scale;
+        self.mark_dirty();
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty.load(Ordering::Acquire)
+    }
+
+    // A dirty transform always has dirty descendants, so a clean one can trust its cache without
+    // looking at its parents. The ancestors are flagged so propagation can find this transform
+    // without visiting clean subtrees. Moving an entity to another parent doesn't go through
+    // here, so call this after re-parenting.
+    pub fn mark_dirty(&self) {
+        if !self.dirty.swap(true, Ordering::AcqRel) {
+            let entity = { self.entity.read().unwrap().clone() };
+            let mut stack = entity.map(|entity| entity.children()).unwrap_or_default();
+
+            while let Some(entity) = stack.pop() {
+                match entity.first_of::<Self>() {
+                    Some(transform) if transform.dirty.swap(true, Ordering::AcqRel) => {}
+                    _ => stack.extend(entity.children()),
+                }
+            }
+        }
+
+        let mut parent = self.parent();
+
+        while let Some(transform) = parent {
+            if transform.dirty_descendants.swap(true, Ordering::AcqRel) {
+                break;
+            }
+
+            parent = transform.parent();
+        }
+    }
+
+    pub fn local_matrix(&self) -> Matrix4<f32> {
+        self.data.read().unwrap().matrix()
+    }
+
+    pub fn parent(&self) -> Option<Arc<Self>> {
+        let mut entity = { self.entity.read().unwrap().clone() }
+            .and_then(|entity| entity.entity.read().unwrap().clone());
+
+        while let Some(e) = entity {
+            if let Some(transform) = e.first_of::<Self>() {
+                return Some(transform);
+            }
+
+            entity = e.entity.read().unwrap().clone();
+        }
+
+        None
+    }
+
+    fn update(&self, parent: Option<(Matrix4<f32>, Quaternion<f32>)>) -> bool {
+        let local = self.data();
+        let parent = parent.unwrap_or_else(|| (Matrix4::identity(), Quaternion::one()));
+        let dirty = self.dirty.swap(false, Ordering::AcqRel);
+
         {
-            let d = self.data.read().unwrap();
+            let cache = self.cache.read().unwrap();
+
+            if !dirty && cache.local == local && cache.parent == parent {
+                return false;
+            }
+        }
+
+        let mut cache = self.cache.write().unwrap();
+
+        cache.local = local;
+        cache.parent = parent;
+        cache.world = parent.0 * local.matrix();
+        cache.rotation = parent.1 * local.rotation;
+
+        true
+    }
+
+    fn world(&self) -> (Matrix4<f32>, Quaternion<f32>) {
+        if self.is_dirty() {
+            let parent = self.parent().map(|parent| parent.world());
+
+            self.update(parent);
+        }
+
+        let cache = self.cache.read().unwrap();
+
+        (cache.world, cache.rotation)
+    }
+
+    pub fn world_matrix(&self) -> Matrix4<f32> {
+        self.world().0
+    }
+
+    pub fn world_position(&self) -> Vector3<f32> {
+        self.world_matrix().w.truncate()
+    }
+
+    pub fn world_rotation(&self) -> Quaternion<f32> {
+        self.world().1
+    }
+
+    pub fn forward(&self) -> Vector3<f32> {
+        self.world_rotation() * -Vector3::unit_z()
+    }
 
-            data.position += d.position;
-            data.rotation += d.rotation;
-            data.scale += d.scale;
+    pub fn right(&self) -> Vector3<f32> {
+        self.world_rotation() * Vector3::unit_x()
+    }
+
+    pub fn up(&self) -> Vector3<f32> {
+        self.world_rotation() * Vector3::unit_y()
+    }
+
+    pub fn look_at(&self, target: Vector3<f32>, up: Vector3<f32>) {
+        let position = self.world_position();
+        let parent_rotation = self
+            .parent()
+            .map(|parent| parent.world_rotation())
+            .unwrap_or_else(Quaternion::one);
+        let offset = position - target;
+
+        // There's no direction to look in when the target sits on top of us.
+        if offset.magnitude2() < f32::EPSILON {
+            return;
         }
 
-        let entity = self.entity.read().unwrap();
+        let back = offset.normalize();
+        let mut right = up.cross(back);
+
+        // Any roll will do when up runs along the view direction, so borrow another axis.
+        if right.magnitude2() < f32::EPSILON {
+            let axis = if back.x.abs() < 0.9 {
+                Vector3::unit_x()
+            } else {
+                Vector3::unit_y()
+            };
+
+            right = back.cross(axis);
+        }
 
-        if let Some(entity) = &*entity {
-            let entity = entity.entity.read().unwrap();
+        let right = right.normalize();
+        let up = back.cross(right);
+        let rotation = Quaternion::from(Matrix3::from_cols(right, up, back));
 
-            if let Some(entity) = &*entity {
-                if let Some(transform) = entity.get_first::<Self>(ecs::id(TRANSFORM_ID)) {
-                    transform.calculate_inner(data);
+        self.set_rotation(parent_rotation.invert() * rotation);
+    }
+
+    pub fn propagate(root: &Arc<Entity>) {
+        let parent = root
+            .first_of::<Self>()
+            .and_then(|transform| transform.parent())
+            .map(|parent| parent.world());
+        let mut stack = vec![(root.clone(), parent, false)];
+
+        // Subtrees with nothing dirty in them and an unchanged parent are skipped; anything
+        // missed here is still brought up to date lazily by `world`.
+        while let Some((entity, parent, moved)) = stack.pop() {
+            let (world, moved) = match entity.first_of::<Self>() {
+                Some(transform) => {
+                    let descendants = transform.dirty_descendants.swap(false, Ordering::AcqRel);
+
+                    if !moved && !descendants && !transform.is_dirty() {
+                        continue;
+                    }
+
+                    let moved = transform.update(parent) || moved;
+                    let cache = transform.cache.read().unwrap();
+
+                    (Some((cache.world, cache.rotation)), moved)
                 }
+
+                None => (parent, moved),
+            };
+
+            for child in entity.children() {
+                stack.push((child, world, moved));
             }
         }
     }
+}
+
+pub struct TransformPropagation;
+
+impl System for TransformPropagation {
+    fn id(&self) -> Arc<String> {
+        ecs::id(TRANSFORM_PROPAGATION_ID)
+    }
+
+    fn access(&self) -> Access {
+        Access::new().write::<Transform>()
+    }
+
+    fn run(&self, root: &Arc<Entity>) {
+        Transform::propagate(root);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{Deg, Rotation3};
+
+    fn transform(position: Vector3<f32>) -> Arc<Transform> {
+        Transform::new(
+            ecs::id("transform"),
+            position,
+            Quaternion::one(),
+            Vector3::new(1.0, 1.0, 1.0),
+        )
+    }
+
+    fn hierarchy() -> (Arc<Entity>, Arc<Transform>, Arc<Transform>) {
+        let parent = Entity::new(ecs::id("parent"));
+        let child = Entity::new(ecs::id("child"));
+        let parent_transform = transform(Vector3::new(1.0, 0.0, 0.0));
+        let child_transform = transform(Vector3::new(0.0, 1.0, 0.0));
+
+        parent.add(&parent_transform);
+        child.add(&child_transform);
+        parent.add(&child);
+
+        (parent, parent_transform, child_transform)
+    }
+
+    #[test]
+    fn children_follow_their_parent() {
+        let (root, parent, child) = hierarchy();
+
+        Transform::propagate(&root);
+        assert_eq!(child.world_position(), Vector3::new(1.0, 1.0, 0.0));
+
+        parent.set_position(Vector3::new(2.0, 0.0, 0.0));
+        assert_eq!(child.world_position(), Vector3::new(2.0, 1.0, 0.0));
+
+        parent.set_rotation(Quaternion::from_angle_z(Deg(90.0)));
+        Transform::propagate(&root);
+        assert!((child.world_position() - Vector3::new(1.0, 0.0, 0.0)).magnitude() < 1e-5);
+    }
+
+    #[test]
+    fn reflected_writes_mark_the_transform_dirty() {
+        let (root, parent, child) = hierarchy();
+
+        Transform::propagate(&root);
+        assert!(!parent.is_dirty());
+
+        parent
+            .set_field("position", serde_json::json!([3.0, 0.0, 0.0]))
+            .unwrap();
+
+        assert!(parent.is_dirty());
+        assert_eq!(child.world_position(), Vector3::new(3.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn propagation_reaches_dirty_descendants_of_clean_parents() {
+        let (root, parent, child) = hierarchy();
+
+        Transform::propagate(&root);
+        child.set_position(Vector3::new(0.0, 2.0, 0.0));
+
+        assert!(parent.dirty_descendants.load(Ordering::Acquire));
+
+        Transform::propagate(&root);
+
+        assert!(!parent.dirty_descendants.load(Ordering::Acquire));
+        assert!(!child.is_dirty());
+        assert_eq!(
+            child.cache.read().unwrap().world.w.truncate(),
+            Vector3::new(1.0, 2.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn look_at_ignores_degenerate_directions() {
+        let transform = transform(Vector3::new(0.0, 1.0, 0.0));
+
+        transform.look_at(Vector3::new(0.0, 1.0, 0.0), Vector3::unit_y());
+        assert_eq!(transform.data().rotation, Quaternion::one());
+
+        transform.look_at(Vector3::new(0.0, 0.0, 0.0), Vector3::unit_y());
+
+        let rotation = transform.data().rotation;
+        let forward = rotation.rotate_vector(-Vector3::unit_z());
+
+        assert!(rotation.s.is_finite() && rotation.v.x.is_finite());
+        assert!((forward - -Vector3::unit_y()).magnitude() < 1e-5);
+    }
+
+    #[test]
+    fn clean_children_do_not_read_their_parents() {
+        let (root, parent, child) = hierarchy();
+
+        Transform::propagate(&root);
+
+        // Bypasses `mark_dirty`, so only a walk up the hierarchy could see the new position.
+        parent.data.write().unwrap().position = Vector3::new(5.0, 0.0, 0.0);
 
-    pub fn calculate(&self) -> TransformData {
-        let mut data = TransformData::new(Vector3::zero(), Vector3::zero(), Vector3::zero());
+        assert!(!child.is_dirty());
+        assert_eq!(child.world_position(), Vector3::new(1.0, 1.0, 0.0));
 
-        self.calculate_inner(&mut data);
+        parent.mark_dirty();
 
-        data
+        assert!(child.is_dirty());
+        assert_eq!(child.world_position(), Vector3::new(5.0, 1.0, 0.0));
+        assert!(!parent.is_dirty());
     }
 }
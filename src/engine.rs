@@ -1,30 +1,46 @@
 use crate::{
-    assets::mesh::{Normal, Vertex},
-    components::{Camera, EventHandler, Light, Model, EVENT_HANDLER_ID, MODEL_ID},
-    ecs::{self, Component, Entity, ENTITY_ID},
+    assets::{
+        mesh::{Normal, Tangent, Vertex},
+        Texture,
+    },
+    bounds::{CullStats, Frustum},
+    capture::Capture,
+    components::{Camera, Clear, EventHandler, Light, Model, Projection},
+    ecs::{Component, Entity},
     error::Error,
     scene::Scene,
     shaders::{
+        clear_vertex,
         fragment::{self, MAX_LIGHTS},
         vertex, Shaders,
     },
+    shadows::{ShadowAtlas, SHADOW_ATLAS_SIZE},
 };
 use cgmath::{Matrix4, SquareMatrix, Vector3, Zero};
-use std::sync::{Arc, RwLock};
+use std::{
+    sync::{Arc, RwLock, Weak},
+    time::Instant,
+};
 use vulkano::{
-    buffer::{cpu_pool::CpuBufferPool, BufferUsage},
+    buffer::{cpu_pool::CpuBufferPool, BufferUsage, CpuAccessibleBuffer},
     command_buffer::{
         pool::standard::StandardCommandPoolBuilder, AutoCommandBufferBuilder, CommandBufferUsage,
         PrimaryAutoCommandBuffer, SubpassContents,
     },
+    device::DeviceOwned,
     device::{physical::PhysicalDevice, Device, DeviceExtensions, Queue},
     format::Format,
     image::{
-        attachment::AttachmentImage, view::ImageView, ImageUsage, SampleCount, SwapchainImage,
+        attachment::AttachmentImage,
+        view::{ImageView, ImageViewAbstract},
+        ImageAccess, ImageUsage, SampleCount, SwapchainImage,
     },
     instance::Instance,
     pipeline::{
-        depth_stencil::DepthStencil, vertex::BuffersDefinition, viewport::Viewport,
+        blend::AttachmentBlend,
+        depth_stencil::{CompareOp, DepthStencil},
+        vertex::BuffersDefinition,
+        viewport::Viewport,
         GraphicsPipeline,
     },
     render_pass::{Framebuffer, FramebufferAbstract, RenderPass, Subpass},
@@ -45,6 +61,8 @@ pub struct InitializedEngine {
     pub lights_array: [fragment::ty::Light; MAX_LIGHTS],
     pub uniform_buffer: CpuBufferPool<vertex::ty::Data>,
     pub frag_uniform_buffer: CpuBufferPool<fragment::ty::Data>,
+    pub shadow_atlas: ShadowAtlas,
+    target_framebuffers: Vec<(Weak<Texture>, Arc<dyn FramebufferAbstract + Send + Sync>)>,
 }
 
 impl InitializedEngine {
@@ -52,11 +70,107 @@ impl InitializedEngine {
         lights_array: [fragment::ty::Light; MAX_LIGHTS],
         uniform_buffer: CpuBufferPool<vertex::ty::Data>,
         frag_uniform_buffer: CpuBufferPool<fragment::ty::Data>,
+        shadow_atlas: ShadowAtlas,
     ) -> Self {
         Self {
             lights_array,
             uniform_buffer,
             frag_uniform_buffer,
+            shadow_atlas,
+            target_framebuffers: Vec::new(),
+        }
+    }
+
+    // Render targets keep their attachments and framebuffer for as long as the texture lives.
+    fn target_framebuffer(
+        &mut self,
+        target: &Arc<Texture>,
+        view: &Arc<ImageView<Arc<AttachmentImage>>>,
+        render_pass: &Arc<RenderPass>,
+    ) -> Result<Arc<dyn FramebufferAbstract + Send + Sync>, Error> {
+        self.target_framebuffers
+            .retain(|(texture, _)| texture.strong_count() > 0);
+
+        if let Some((_, framebuffer)) = self
+            .target_framebuffers
+            .iter()
+            .find(|(texture, _)| texture.as_ptr() == Arc::as_ptr(target))
+        {
+            return Ok(framebuffer.clone());
+        }
+
+        let attachments = render_pass.desc().attachments();
+        let format = attachments[2].format;
+        let target_format = ImageAccess::format(ImageView::image(view));
+
+        if target_format != format {
+            return Err(Error::TargetFormatMismatch(target_format));
+        }
+
+        let framebuffer = Engine::create_framebuffers(
+            render_pass.device().clone(),
+            format,
+            render_pass.clone(),
+            view.clone(),
+            attachments[0].samples,
+            &ImageView::image(view).dimensions().width_height(),
+        )?;
+
+        self.target_framebuffers
+            .push((Arc::downgrade(target), framebuffer.clone()));
+
+        Ok(framebuffer)
+    }
+
+    pub fn from_device(
+        device: Arc<Device>,
+        shaders: Arc<Shaders>,
+        shadow_atlas_size: u32,
+    ) -> Result<Self, Error> {
+        let uniform_buffer =
+            CpuBufferPool::<vertex::ty::Data>::new(device.clone(), BufferUsage::uniform_buffer());
+        let frag_uniform_buffer =
+            CpuBufferPool::<fragment::ty::Data>::new(device.clone(), BufferUsage::uniform_buffer());
+        let lights_array = [fragment::ty::Light {
+            proj: Matrix4::identity().into(),
+            position: Vector3::zero().into(),
+            kind: 0,
+            direction: Vector3::zero().into(),
+            range: 0.0,
+            color: Vector3::zero().into(),
+            intensity: 0.0,
+            inner_cutoff: 0.0,
+            outer_cutoff: 0.0,
+            shadow: -1,
+            bias: 0.0,
+            normal_bias: 0.0,
+            shadow_strength: 0.0,
+            _dummy0: [0; 8],
+        }; MAX_LIGHTS];
+        let shadow_atlas = ShadowAtlas::new(device, shaders, shadow_atlas_size)?;
+
+        Ok(Self::new(
+            lights_array,
+            uniform_buffer,
+            frag_uniform_buffer,
+            shadow_atlas,
+        ))
+    }
+}
+
+pub struct Pipelines {
+    pub standard: Arc<GraphicsPipeline>,
+    pub reverse_z: Arc<GraphicsPipeline>,
+    pub clear: Arc<GraphicsPipeline>,
+    pub clear_depth: Arc<GraphicsPipeline>,
+}
+
+impl Pipelines {
+    pub fn get(&self, projection: &Projection) -> Arc<GraphicsPipeline> {
+        if projection.reverse_z() {
+            self.reverse_z.clone()
+        } else {
+            self.standard.clone()
         }
     }
 }
@@ -73,6 +187,8 @@ pub struct Engine {
     #[allow(clippy::type_complexity)]
     pub images: RwLock<Vec<Arc<ImageView<Arc<SwapchainImage<Window>>>>>>,
     pub scene: RwLock<Arc<Scene>>,
+    pub capture: Arc<Capture>,
+    pub shadow_atlas_size: u32,
 }
 
 impl Engine {
@@ -118,7 +234,11 @@ impl Engine {
                 .format(format)
                 .dimensions(dimensions)
                 .layers(1)
-                .usage(ImageUsage::color_attachment())
+                .usage(ImageUsage {
+                    color_attachment: true,
+                    transfer_source: caps.supported_usage_flags.transfer_source,
+                    ..ImageUsage::none()
+                })
                 .transform(SurfaceTransform::Identity)
                 .clipped(true)
                 .color_space(ColorSpace::SrgbNonLinear)
@@ -130,12 +250,46 @@ impl Engine {
 
             (swapchain, images)
         };
-        let render_pass = Arc::new(vulkano::single_pass_renderpass!(device.clone(),
+        let render_pass =
+            Self::create_render_pass(device.clone(), swapchain.format(), sample_count)?;
+
+        Ok(Self {
+            physical_index,
+            sample_count,
+            event_loop,
+            device,
+            queue,
+            surface,
+            render_pass,
+            swapchain: RwLock::new(swapchain),
+            images: RwLock::new(images),
+            scene: RwLock::new(scene),
+            capture: Capture::new(),
+            shadow_atlas_size: SHADOW_ATLAS_SIZE,
+        })
+    }
+
+    pub fn first(
+        surface: Arc<Surface<Window>>,
+        instance: Arc<Instance>,
+        event_loop: EventLoop<()>,
+        scene: Arc<Scene>,
+        sample_count: SampleCount,
+    ) -> Result<Self, Error> {
+        Self::new(0, surface, instance, event_loop, scene, sample_count)
+    }
+
+    pub(crate) fn create_render_pass(
+        device: Arc<Device>,
+        format: Format,
+        sample_count: SampleCount,
+    ) -> Result<Arc<RenderPass>, Error> {
+        let render_pass = Arc::new(vulkano::single_pass_renderpass!(device,
             attachments: {
                 intermediary: {
                     load: Clear,
                     store: Store,
-                    format: swapchain.format(),
+                    format: format,
                     samples: sample_count,
                 },
                 depth: {
@@ -147,7 +301,7 @@ impl Engine {
                 color: {
                     load: Clear,
                     store: Store,
-                    format: swapchain.format(),
+                    format: format,
                     samples: 1,
                 }
             },
@@ -159,55 +313,70 @@ impl Engine {
             }
         )?);
 
-        Ok(Self {
-            physical_index,
-            sample_count,
-            event_loop,
-            device,
-            queue,
-            surface,
-            render_pass,
-            swapchain: RwLock::new(swapchain),
-            images: RwLock::new(images),
-            scene: RwLock::new(scene),
-        })
+        Ok(render_pass)
     }
 
-    pub fn first(
-        surface: Arc<Surface<Window>>,
-        instance: Arc<Instance>,
-        event_loop: EventLoop<()>,
-        scene: Arc<Scene>,
-        sample_count: SampleCount,
-    ) -> Result<Self, Error> {
-        Self::new(0, surface, instance, event_loop, scene, sample_count)
+    pub(crate) fn create_pipelines(
+        render_pass: Arc<RenderPass>,
+        device: Arc<Device>,
+        shaders: Arc<Shaders>,
+    ) -> Result<Pipelines, Error> {
+        let standard = Self::create_pipeline(
+            render_pass.clone(),
+            device.clone(),
+            shaders.clone(),
+            DepthStencil::simple_depth_test(),
+        )?;
+        let reverse_z = Self::create_pipeline(
+            render_pass.clone(),
+            device.clone(),
+            shaders.clone(),
+            DepthStencil {
+                depth_compare: CompareOp::Greater,
+                ..DepthStencil::simple_depth_test()
+            },
+        )?;
+        let clear = Self::create_clear_pipeline(
+            render_pass.clone(),
+            device.clone(),
+            shaders.clone(),
+            AttachmentBlend::pass_through(),
+        )?;
+        let clear_depth = Self::create_clear_pipeline(
+            render_pass,
+            device,
+            shaders,
+            AttachmentBlend::ignore_source(),
+        )?;
+
+        Ok(Pipelines {
+            standard,
+            reverse_z,
+            clear,
+            clear_depth,
+        })
     }
 
-    fn window_size_dependent_setup(
+    fn create_pipeline(
         render_pass: Arc<RenderPass>,
         device: Arc<Device>,
         shaders: Arc<Shaders>,
-        swapchain: Arc<Swapchain<Window>>,
+        depth_stencil: DepthStencil,
     ) -> Result<Arc<GraphicsPipeline>, Error> {
-        let dimensions = swapchain.dimensions();
         let pipeline = Arc::new(
             GraphicsPipeline::start()
                 .vertex_input(
                     BuffersDefinition::new()
                         .vertex::<Vertex>()
-                        .vertex::<Normal>(),
+                        .vertex::<Normal>()
+                        .vertex::<Tangent>(),
                 )
                 .vertex_shader(shaders.vertex.main_entry_point(), ())
                 .triangle_list()
                 .viewports_dynamic_scissors_irrelevant(1)
                 .fragment_shader(shaders.fragment.main_entry_point(), ())
                 .render_pass(Subpass::from(render_pass, 0).unwrap())
-                .viewports(vec![Viewport {
-                    origin: [0.0, 0.0],
-                    dimensions: [dimensions[0] as f32, dimensions[1] as f32],
-                    depth_range: 0.0..1.0,
-                }])
-                .depth_stencil(DepthStencil::simple_depth_test())
+                .depth_stencil(depth_stencil)
                 .blend_alpha_blending()
                 .build(device)?,
         );
@@ -215,14 +384,42 @@ impl Engine {
         Ok(pipeline)
     }
 
-    fn create_framebuffers(
+    fn create_clear_pipeline(
+        render_pass: Arc<RenderPass>,
         device: Arc<Device>,
-        swapchain: Arc<Swapchain<Window>>,
+        shaders: Arc<Shaders>,
+        blend: AttachmentBlend,
+    ) -> Result<Arc<GraphicsPipeline>, Error> {
+        let pipeline = Arc::new(
+            GraphicsPipeline::start()
+                .vertex_input(BuffersDefinition::new())
+                .vertex_shader(shaders.clear_vertex.main_entry_point(), ())
+                .triangle_list()
+                .viewports_dynamic_scissors_irrelevant(1)
+                .fragment_shader(shaders.clear_fragment.main_entry_point(), ())
+                .render_pass(Subpass::from(render_pass, 0).unwrap())
+                .depth_stencil(DepthStencil {
+                    depth_compare: CompareOp::Always,
+                    ..DepthStencil::simple_depth_test()
+                })
+                .blend_collective(blend)
+                .build(device)?,
+        );
+
+        Ok(pipeline)
+    }
+
+    pub(crate) fn create_framebuffers<C>(
+        device: Arc<Device>,
+        format: Format,
         render_pass: Arc<RenderPass>,
-        color: Arc<ImageView<Arc<SwapchainImage<Window>>>>,
+        color: C,
         sample_count: SampleCount,
         dimensions: &[u32; 2],
-    ) -> Result<Arc<dyn FramebufferAbstract + Send + Sync>, Error> {
+    ) -> Result<Arc<dyn FramebufferAbstract + Send + Sync>, Error>
+    where
+        C: ImageViewAbstract + Send + Sync + 'static,
+    {
         let usage = ImageUsage {
             transient_attachment: true,
             input_attachment: true,
@@ -233,7 +430,7 @@ impl Engine {
             device.clone(),
             *dimensions,
             sample_count,
-            swapchain.format(),
+            format,
             usage,
         )?)?;
         let depth = ImageView::new(AttachmentImage::multisampled_with_usage(
@@ -254,19 +451,15 @@ impl Engine {
         Ok(framebuffer)
     }
 
-    fn handle_events(entity: Arc<Entity>, event: &Event<()>) {
-        for event_handler in &*entity.get_type::<EventHandler>(ecs::id(EVENT_HANDLER_ID)) {
+    fn handle_events(entity: &Arc<Entity>, event: &Event<()>) {
+        for (_, event_handler) in entity.query::<&EventHandler>() {
             event_handler.handle(event);
         }
-
-        for entity in &*entity.get_type::<Entity>(ecs::id(ENTITY_ID)) {
-            Self::handle_events(entity.clone(), event);
-        }
     }
 
     fn draw_entities(
         initialized_engine: &mut InitializedEngine,
-        entities: Vec<Arc<Entity>>,
+        entity: &Arc<Entity>,
         camera: Arc<Camera>,
         builder: &mut AutoCommandBufferBuilder<
             PrimaryAutoCommandBuffer,
@@ -275,10 +468,29 @@ impl Engine {
         pipeline: &GraphicsPipeline,
         lights: &Vec<Arc<Light>>,
         dimensions: &[u32; 2],
-    ) {
-        for entity in &*entities {
-            for model in &*entity.get_type::<Model>(ecs::id(MODEL_ID)) {
-                if model.data.read().unwrap().visible {
+    ) -> CullStats {
+        let aspect_ratio = dimensions[0] as f32 / dimensions[1] as f32;
+        let frustum = Frustum::from_matrix(&(camera.projection(aspect_ratio) * camera.view()));
+        let mut stats = CullStats::default();
+        let target = camera.data.read().unwrap().target.clone();
+
+        for (_, model) in entity.query::<&Model>() {
+            // Sampling the texture this camera renders into would read and write it in one pass.
+            if let Some(target) = &target {
+                if model.samples(target) {
+                    continue;
+                }
+            }
+
+            if model.data.read().unwrap().visible {
+                let inside = model
+                    .world_bounds()
+                    .map(|(aabb, sphere)| {
+                        frustum.intersects_sphere(&sphere) && frustum.intersects_aabb(&aabb)
+                    })
+                    .unwrap_or(true);
+
+                if inside {
                     model.draw(
                         initialized_engine,
                         camera.clone(),
@@ -287,59 +499,243 @@ impl Engine {
                         lights,
                         dimensions,
                     );
+
+                    stats.drawn += 1;
+                } else {
+                    stats.culled += 1;
                 }
             }
+        }
+
+        stats
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn draw_camera(
+        initialized_engine: &mut InitializedEngine,
+        scene: &Scene,
+        camera: &Arc<Camera>,
+        builder: &mut AutoCommandBufferBuilder<
+            PrimaryAutoCommandBuffer,
+            StandardCommandPoolBuilder,
+        >,
+        pipelines: &Pipelines,
+        lights: &Vec<Arc<Light>>,
+        dimensions: &[u32; 2],
+        depth: &mut f32,
+    ) -> Result<(), Error> {
+        let (viewport, clear, projection) = {
+            let data = camera.data.read().unwrap();
+
+            (data.viewport, data.clear, data.projection)
+        };
+        let (origin, size) = viewport.pixels(dimensions);
+        let clear = match clear {
+            Clear::Background => {
+                Some((pipelines.clear.clone(), (*scene.bg.read().unwrap()).into()))
+            }
+            Clear::Color(color) => Some((pipelines.clear.clone(), color.into())),
+            Clear::DepthOnly => Some((pipelines.clear_depth.clone(), [0.0; 4])),
+            // Depth left behind by a camera with the other depth convention is meaningless here.
+            Clear::Nothing if *depth != projection.clear_depth() => {
+                Some((pipelines.clear_depth.clone(), [0.0; 4]))
+            }
+            Clear::Nothing => None,
+        };
+
+        *depth = projection.clear_depth();
+
+        builder.set_viewport(
+            0,
+            [Viewport {
+                origin,
+                dimensions: size,
+                depth_range: 0.0..1.0,
+            }],
+        );
+
+        if let Some((pipeline, color)) = clear {
+            builder
+                .bind_pipeline_graphics(pipeline.clone())
+                .push_constants(
+                    pipeline.layout().clone(),
+                    0,
+                    clear_vertex::ty::Clear {
+                        color,
+                        depth: projection.clear_depth(),
+                    },
+                )
+                .draw(3, 1, 0, 0)?;
+        }
+
+        let pipeline = pipelines.get(&projection);
+
+        builder.bind_pipeline_graphics(pipeline.clone());
+
+        let stats = Self::draw_entities(
+            initialized_engine,
+            &scene.root,
+            camera.clone(),
+            builder,
+            &pipeline,
+            lights,
+            &[size[0].max(1.0) as u32, size[1].max(1.0) as u32],
+        );
+        let mut scene_stats = scene.stats.write().unwrap();
+
+        scene_stats.drawn += stats.drawn;
+        scene_stats.culled += stats.culled;
 
-            Self::draw_entities(
+        Ok(())
+    }
+
+    fn draw_cameras(
+        initialized_engine: &mut InitializedEngine,
+        scene: &Scene,
+        cameras: &[Arc<Camera>],
+        builder: &mut AutoCommandBufferBuilder<
+            PrimaryAutoCommandBuffer,
+            StandardCommandPoolBuilder,
+        >,
+        pipelines: &Pipelines,
+        framebuffer: Arc<dyn FramebufferAbstract + Send + Sync>,
+        dimensions: &[u32; 2],
+    ) -> Result<(), Error> {
+        let lights = scene.get_lights();
+        let bg: [f32; 4] = (*scene.bg.read().unwrap()).into();
+        let mut depth = cameras
+            .first()
+            .map(|camera| camera.data.read().unwrap().projection.clear_depth())
+            .unwrap_or(1.0);
+
+        builder.begin_render_pass(
+            framebuffer,
+            SubpassContents::Inline,
+            vec![bg.into(), depth.into(), [0.0_f32; 4].into()],
+        )?;
+
+        for camera in cameras {
+            Self::draw_camera(
                 initialized_engine,
-                entity.get_type(ecs::id(ENTITY_ID)),
-                camera.clone(),
+                scene,
+                camera,
                 builder,
-                pipeline,
-                lights,
+                pipelines,
+                &lights,
                 dimensions,
-            );
+                &mut depth,
+            )?;
         }
+
+        builder.end_render_pass()?;
+
+        Ok(())
+    }
+
+    pub(crate) fn draw_scene(
+        initialized_engine: &mut InitializedEngine,
+        scene: &Scene,
+        builder: &mut AutoCommandBufferBuilder<
+            PrimaryAutoCommandBuffer,
+            StandardCommandPoolBuilder,
+        >,
+        pipelines: &Pipelines,
+        render_pass: Arc<RenderPass>,
+        framebuffer: Arc<dyn FramebufferAbstract + Send + Sync>,
+        dimensions: &[u32; 2],
+    ) -> Result<(), Error> {
+        let cameras = scene.cameras();
+
+        *scene.stats.write().unwrap() = CullStats::default();
+
+        let lights = scene.get_lights();
+        let shadows = initialized_engine
+            .shadow_atlas
+            .draw(&scene.root, &lights, builder)?;
+
+        for (i, (light, shadow)) in lights.iter().zip(shadows).take(MAX_LIGHTS).enumerate() {
+            if let Some(uniform) = light.uniform(shadow) {
+                initialized_engine.lights_array[i] = uniform;
+            }
+        }
+
+        let mut targets: Vec<Arc<Texture>> = Vec::new();
+
+        for camera in &cameras {
+            if let Some(target) = &camera.data.read().unwrap().target {
+                if !targets.iter().any(|t| Arc::ptr_eq(t, target)) {
+                    targets.push(target.clone());
+                }
+            }
+        }
+
+        for target in targets {
+            if let Some(view) = &target.target {
+                let target_dimensions = ImageView::image(view).dimensions().width_height();
+                let target_framebuffer =
+                    initialized_engine.target_framebuffer(&target, view, &render_pass)?;
+                let target_cameras = cameras
+                    .iter()
+                    .filter(|camera| {
+                        camera
+                            .data
+                            .read()
+                            .unwrap()
+                            .target
+                            .as_ref()
+                            .map(|t| Arc::ptr_eq(t, &target))
+                            .unwrap_or(false)
+                    })
+                    .cloned()
+                    .collect::<Vec<_>>();
+
+                Self::draw_cameras(
+                    initialized_engine,
+                    scene,
+                    &target_cameras,
+                    builder,
+                    pipelines,
+                    target_framebuffer,
+                    &target_dimensions,
+                )?;
+            }
+        }
+
+        let cameras = cameras
+            .into_iter()
+            .filter(|camera| camera.data.read().unwrap().target.is_none())
+            .collect::<Vec<_>>();
+
+        Self::draw_cameras(
+            initialized_engine,
+            scene,
+            &cameras,
+            builder,
+            pipelines,
+            framebuffer,
+            dimensions,
+        )
     }
 
     pub fn init(self, shaders: Arc<Shaders>) -> Result<(), Error> {
         self.scene.read().unwrap().root.on_init();
 
-        let mut pipeline = Self::window_size_dependent_setup(
+        let pipelines = Self::create_pipelines(
             self.render_pass.clone(),
             self.device.clone(),
             shaders.clone(),
-            self.swapchain.read().unwrap().clone(),
         )?;
-        let uniform_buffer = CpuBufferPool::<vertex::ty::Data>::new(
-            self.device.clone(),
-            BufferUsage::uniform_buffer(),
-        );
-        let frag_uniform_buffer = CpuBufferPool::<fragment::ty::Data>::new(
-            self.device.clone(),
-            BufferUsage::uniform_buffer(),
-        );
-        let lights_array = [fragment::ty::Light {
-            position: Matrix4::identity().into(),
-            rotation: Matrix4::identity().into(),
-            proj: Matrix4::identity().into(),
-            color: Vector3::zero().into(),
-            directional: 0,
-            cutoff: 0.0,
-            outer_cutoff: 0.0,
-            intensity: 0.0,
-            attenuation: 0.0,
-        }; MAX_LIGHTS];
         let mut initialized_engine =
-            InitializedEngine::new(lights_array, uniform_buffer, frag_uniform_buffer);
+            InitializedEngine::from_device(self.device.clone(), shaders, self.shadow_atlas_size)?;
         let mut recreate_swapchain = false;
         let mut previous_frame_end = Some(sync::now(self.device.clone()).boxed());
+        let mut last_frame = Instant::now();
 
         self.event_loop.run(move |event, _, control_flow| {
             {
                 let scene = self.scene.read().unwrap();
 
-                Self::handle_events(scene.root.clone(), &event);
+                Self::handle_events(&scene.root, &event);
             }
 
             match event {
@@ -362,8 +758,20 @@ impl Engine {
                     let mut swapchain = self.swapchain.write().unwrap();
                     let mut images = self.images.write().unwrap();
                     let scene = self.scene.read().unwrap();
+                    let now = Instant::now();
 
-                    scene.root.on_update();
+                    scene
+                        .time
+                        .write()
+                        .unwrap()
+                        .advance((now - last_frame).as_secs_f32());
+
+                    scene
+                        .schedule()
+                        .run(&scene.root)
+                        .expect("scene schedules are validated when they're set");
+
+                    last_frame = now;
 
                     if recreate_swapchain {
                         let (new_swapchain, new_images) =
@@ -379,14 +787,6 @@ impl Engine {
                             .map(|i| ImageView::new(i).unwrap())
                             .collect::<Vec<_>>();
 
-                        pipeline = Self::window_size_dependent_setup(
-                            self.render_pass.clone(),
-                            self.device.clone(),
-                            shaders.clone(),
-                            swapchain.clone(),
-                        )
-                        .unwrap();
-
                         recreate_swapchain = false;
                     }
 
@@ -407,13 +807,9 @@ impl Engine {
                         recreate_swapchain = true;
                     }
 
-                    let lights = scene.get_lights();
-                    let entities = scene.root.get_type::<Entity>(ecs::id(ENTITY_ID));
-                    let camera = { scene.camera.read().unwrap().clone() };
-                    let bg: [f32; 4] = (*scene.bg.read().unwrap()).into();
                     let framebuffer = Self::create_framebuffers(
                         self.device.clone(),
-                        swapchain.clone(),
+                        swapchain.format(),
                         self.render_pass.clone(),
                         images[image_num].clone(),
                         self.sample_count,
@@ -427,26 +823,34 @@ impl Engine {
                     )
                     .unwrap();
 
-                    builder
-                        .bind_pipeline_graphics(pipeline.clone())
-                        .begin_render_pass(
-                            framebuffer.clone(),
-                            SubpassContents::Inline,
-                            vec![bg.into(), 1.0_f32.into(), [0.0_f32; 4].into()],
-                        )
-                        .unwrap();
-
-                    Self::draw_entities(
+                    Self::draw_scene(
                         &mut initialized_engine,
-                        entities,
-                        camera,
+                        &scene,
                         &mut builder,
-                        &pipeline,
-                        &lights,
+                        &pipelines,
+                        self.render_pass.clone(),
+                        framebuffer,
                         &dimensions,
-                    );
+                    )
+                    .unwrap();
 
-                    builder.end_render_pass().unwrap();
+                    let color = ImageView::image(&images[image_num]).clone();
+                    let capture_buffer =
+                        if self.capture.pending() && color.inner().image.usage().transfer_source {
+                            let buffer = CpuAccessibleBuffer::from_iter(
+                                self.device.clone(),
+                                BufferUsage::transfer_destination(),
+                                false,
+                                (0..dimensions[0] * dimensions[1] * 4).map(|_| 0u8),
+                            )
+                            .unwrap();
+
+                            builder.copy_image_to_buffer(color, buffer.clone()).unwrap();
+
+                            Some(buffer)
+                        } else {
+                            None
+                        };
 
                     let command_buffer = builder.build().unwrap();
                     let future = previous_frame_end
@@ -460,6 +864,16 @@ impl Engine {
 
                     match future {
                         Ok(future) => {
+                            if let Some(buffer) = capture_buffer {
+                                future.wait(None).unwrap();
+
+                                self.capture.fulfill(
+                                    dimensions,
+                                    &buffer.read().unwrap(),
+                                    swapchain.format(),
+                                );
+                            }
+
                             previous_frame_end = Some(future.boxed());
                         }
 
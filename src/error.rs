@@ -1,14 +1,27 @@
+use crate::{
+    components::LightType,
+    ecs::{ReflectError, ScheduleError, WorldError},
+};
 use obj::ObjError;
-use png::DecodingError;
+use png::{DecodingError, EncodingError};
+use serde_json::Error as JsonError;
 use std::io;
 use vulkano::{
+    buffer::cpu_access::ReadLockError,
+    command_buffer::{
+        AutoCommandBufferBuilderContextError, BeginRenderPassError, BuildError,
+        CommandBufferExecError, CopyBufferImageError, DrawError, DrawIndexedError,
+    },
     device::DeviceCreationError,
+    format::Format,
     image::{sys::ImageCreationError, view::ImageViewCreationError},
     instance::InstanceCreationError,
     memory::DeviceMemoryAllocError,
     pipeline::GraphicsPipelineCreationError,
     render_pass::{FramebufferCreationError, RenderPassCreationError},
+    sampler::SamplerCreationError,
     swapchain::SwapchainCreationError,
+    sync::FlushError,
     OomError,
 };
 use vulkano_win::CreationError;
@@ -28,7 +41,33 @@ pub enum Error {
     ImageCreationError(ImageCreationError),
     ImageViewCreationError(ImageViewCreationError),
     DecodingError(DecodingError),
+    EncodingError(EncodingError),
     FramebufferCreationError(FramebufferCreationError),
+    BeginRenderPassError(BeginRenderPassError),
+    AutoCommandBufferBuilderContextError(AutoCommandBufferBuilderContextError),
+    CopyBufferImageError(CopyBufferImageError),
+    BuildError(BuildError),
+    CommandBufferExecError(CommandBufferExecError),
+    FlushError(FlushError),
+    ReadLockError(ReadLockError),
+    ScheduleError(ScheduleError),
+    JsonError(JsonError),
+    ReflectError(ReflectError),
+    WorldError(WorldError),
+    MissingAsset(String),
+    MissingCamera(String),
+    InvalidOverride(String),
+    DrawError(DrawError),
+    DrawIndexedError(DrawIndexedError),
+    SamplerCreationError(SamplerCreationError),
+    TargetFormatMismatch(Format),
+    UnsupportedFormat(String),
+    InvalidGltf(String),
+    InvalidMesh(String),
+    UnsupportedShadows(LightType),
+    MissingPhysicalDevice(usize),
+    MissingQueueFamily,
+    MissingQueue,
 }
 
 impl From<InstanceCreationError> for Error {
@@ -109,8 +148,98 @@ impl From<DecodingError> for Error {
     }
 }
 
+impl From<EncodingError> for Error {
+    fn from(e: EncodingError) -> Self {
+        Self::EncodingError(e)
+    }
+}
+
 impl From<FramebufferCreationError> for Error {
     fn from(e: FramebufferCreationError) -> Self {
         Self::FramebufferCreationError(e)
     }
 }
+
+impl From<BeginRenderPassError> for Error {
+    fn from(e: BeginRenderPassError) -> Self {
+        Self::BeginRenderPassError(e)
+    }
+}
+
+impl From<AutoCommandBufferBuilderContextError> for Error {
+    fn from(e: AutoCommandBufferBuilderContextError) -> Self {
+        Self::AutoCommandBufferBuilderContextError(e)
+    }
+}
+
+impl From<CopyBufferImageError> for Error {
+    fn from(e: CopyBufferImageError) -> Self {
+        Self::CopyBufferImageError(e)
+    }
+}
+
+impl From<BuildError> for Error {
+    fn from(e: BuildError) -> Self {
+        Self::BuildError(e)
+    }
+}
+
+impl From<CommandBufferExecError> for Error {
+    fn from(e: CommandBufferExecError) -> Self {
+        Self::CommandBufferExecError(e)
+    }
+}
+
+impl From<FlushError> for Error {
+    fn from(e: FlushError) -> Self {
+        Self::FlushError(e)
+    }
+}
+
+impl From<ReadLockError> for Error {
+    fn from(e: ReadLockError) -> Self {
+        Self::ReadLockError(e)
+    }
+}
+
+impl From<ScheduleError> for Error {
+    fn from(e: ScheduleError) -> Self {
+        Self::ScheduleError(e)
+    }
+}
+
+impl From<JsonError> for Error {
+    fn from(e: JsonError) -> Self {
+        Self::JsonError(e)
+    }
+}
+
+impl From<ReflectError> for Error {
+    fn from(e: ReflectError) -> Self {
+        Self::ReflectError(e)
+    }
+}
+
+impl From<WorldError> for Error {
+    fn from(e: WorldError) -> Self {
+        Self::WorldError(e)
+    }
+}
+
+impl From<DrawError> for Error {
+    fn from(e: DrawError) -> Self {
+        Self::DrawError(e)
+    }
+}
+
+impl From<DrawIndexedError> for Error {
+    fn from(e: DrawIndexedError) -> Self {
+        Self::DrawIndexedError(e)
+    }
+}
+
+impl From<SamplerCreationError> for Error {
+    fn from(e: SamplerCreationError) -> Self {
+        Self::SamplerCreationError(e)
+    }
+}
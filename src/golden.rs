@@ -0,0 +1,234 @@
+use crate::{
+    assets::Library,
+    capture::Frame,
+    ecs::Registry,
+    error::Error,
+    headless::{HeadlessEngine, HEADLESS_FORMAT},
+    scene::Scene,
+};
+use std::{
+    fs::{self, File},
+    io::BufReader,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use vulkano::{
+    device::{DeviceOwned, Queue},
+    sampler::Sampler,
+};
+
+pub struct Comparison {
+    pub mismatched: usize,
+    pub max_difference: u8,
+    pub passed: bool,
+}
+
+pub struct Golden {
+    pub reference_dir: PathBuf,
+    pub output_dir: PathBuf,
+    pub frames: usize,
+    pub delta: f32,
+    pub tolerance: u8,
+    pub max_mismatched: usize,
+    pub update: bool,
+}
+
+impl Golden {
+    pub fn new<R, O>(reference_dir: R, output_dir: O) -> Self
+    where
+        R: AsRef<Path>,
+        O: AsRef<Path>,
+    {
+        Self {
+            reference_dir: reference_dir.as_ref().to_path_buf(),
+            output_dir: output_dir.as_ref().to_path_buf(),
+            frames: 1,
+            delta: 1.0 / 60.0,
+            tolerance: 2,
+            max_mismatched: 0,
+            update: false,
+        }
+    }
+
+    pub fn render<F>(&self, engine: &mut HeadlessEngine, build: F) -> Result<Frame, Error>
+    where
+        F: FnOnce(Arc<Queue>) -> Result<Arc<Scene>, Error>,
+    {
+        let scene = build(engine.queue.clone())?;
+
+        engine.set_scene(scene);
+        engine.delta = self.delta;
+
+        let mut frame = engine.render_frame()?;
+
+        for _ in 1..self.frames {
+            frame = engine.render_frame()?;
+        }
+
+        Ok(frame)
+    }
+
+    pub fn compare(&self, name: &str, frame: &Frame) -> Result<Comparison, Error> {
+        let reference_path = self.reference_dir.join(format!("{}.png", name));
+
+        if self.update {
+            fs::create_dir_all(&self.reference_dir)?;
+            frame.save_png(&reference_path)?;
+
+            return Ok(Comparison {
+                mismatched: 0,
+                max_difference: 0,
+                passed: true,
+            });
+        }
+
+        if !reference_path.exists() {
+            fs::create_dir_all(&self.output_dir)?;
+            frame.save_png(self.output_dir.join(format!("{}.actual.png", name)))?;
+
+            return Err(Error::MissingAsset(reference_path.display().to_string()));
+        }
+
+        let reference = Frame::load_png(&reference_path)?;
+        let comparison = if reference.dimensions == frame.dimensions {
+            let mut diff = Vec::with_capacity(frame.data.len());
+            let mut mismatched = 0;
+            let mut max_difference = 0;
+
+            for (a, b) in frame
+                .data
+                .chunks_exact(4)
+                .zip(reference.data.chunks_exact(4))
+            {
+                let difference = a
+                    .iter()
+                    .zip(b)
+                    .map(|(a, b)| a.abs_diff(*b))
+                    .max()
+                    .unwrap_or(0);
+
+                max_difference = max_difference.max(difference);
+
+                if difference > self.tolerance {
+                    mismatched += 1;
+                    diff.extend_from_slice(&[255, 0, 0, 255]);
+                } else {
+                    diff.extend_from_slice(&[a[0] / 4, a[1] / 4, a[2] / 4, 255]);
+                }
+            }
+
+            if mismatched > self.max_mismatched {
+                fs::create_dir_all(&self.output_dir)?;
+                Frame::new(frame.dimensions, diff)
+                    .save_png(self.output_dir.join(format!("{}.diff.png", name)))?;
+            }
+
+            Comparison {
+                mismatched,
+                max_difference,
+                passed: mismatched <= self.max_mismatched,
+            }
+        } else {
+            Comparison {
+                mismatched: (frame.dimensions[0] * frame.dimensions[1]) as usize,
+                max_difference: u8::MAX,
+                passed: false,
+            }
+        };
+
+        if !comparison.passed {
+            fs::create_dir_all(&self.output_dir)?;
+            frame.save_png(self.output_dir.join(format!("{}.actual.png", name)))?;
+        }
+
+        Ok(comparison)
+    }
+
+    pub fn check<F>(
+        &self,
+        engine: &mut HeadlessEngine,
+        name: &str,
+        build: F,
+    ) -> Result<Comparison, Error>
+    where
+        F: FnOnce(Arc<Queue>) -> Result<Arc<Scene>, Error>,
+    {
+        let frame = self.render(engine, build)?;
+
+        self.compare(name, &frame)
+    }
+
+    // Asset paths in the scene resolve relative to the scene file.
+    pub fn check_scene<P>(
+        &self,
+        engine: &mut HeadlessEngine,
+        name: &str,
+        path: P,
+        registry: &Registry,
+    ) -> Result<Comparison, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let root = path.parent().unwrap_or_else(|| Path::new("")).to_path_buf();
+
+        self.check(engine, name, |queue| {
+            let sampler = Sampler::simple_repeat_linear(queue.device().clone());
+            let library = Library::new(queue, sampler, HEADLESS_FORMAT, root);
+
+            Scene::load(BufReader::new(File::open(path)?), Some(&library), registry)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn golden(name: &str) -> Golden {
+        let dir = env::temp_dir().join(format!("wrench-golden-{}-{}", name, std::process::id()));
+
+        Golden::new(dir.join("reference"), dir.join("output"))
+    }
+
+    fn frame(value: u8) -> Frame {
+        Frame::new([2, 2], vec![value; 16])
+    }
+
+    #[test]
+    fn missing_references_fail_unless_updating() {
+        let mut golden = golden("missing");
+
+        assert!(matches!(
+            golden.compare("scene", &frame(10)),
+            Err(Error::MissingAsset(_))
+        ));
+        assert!(golden.output_dir.join("scene.actual.png").exists());
+        assert!(!golden.reference_dir.join("scene.png").exists());
+
+        golden.update = true;
+        assert!(golden.compare("scene", &frame(10)).unwrap().passed);
+
+        golden.update = false;
+        assert!(golden.compare("scene", &frame(10)).unwrap().passed);
+    }
+
+    #[test]
+    fn differences_beyond_the_tolerance_fail() {
+        let mut golden = golden("tolerance");
+
+        golden.update = true;
+        golden.compare("scene", &frame(10)).unwrap();
+        golden.update = false;
+
+        assert!(golden.compare("scene", &frame(12)).unwrap().passed);
+
+        let comparison = golden.compare("scene", &frame(20)).unwrap();
+
+        assert!(!comparison.passed);
+        assert_eq!(comparison.mismatched, 4);
+        assert_eq!(comparison.max_difference, 10);
+        assert!(golden.output_dir.join("scene.diff.png").exists());
+    }
+}
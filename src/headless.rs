@@ -0,0 +1,174 @@
+use crate::{
+    capture::Frame,
+    ecs::Component,
+    engine::{Engine, InitializedEngine, Pipelines},
+    error::Error,
+    scene::Scene,
+    shaders::Shaders,
+    shadows::SHADOW_ATLAS_SIZE,
+};
+use std::sync::{Arc, RwLock};
+use vulkano::{
+    buffer::{BufferUsage, CpuAccessibleBuffer},
+    command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, PrimaryCommandBuffer},
+    device::{physical::PhysicalDevice, Device, DeviceExtensions, Queue},
+    format::Format,
+    image::{attachment::AttachmentImage, view::ImageView, ImageUsage, SampleCount},
+    instance::{Instance, InstanceExtensions},
+    render_pass::RenderPass,
+    sync::GpuFuture,
+    Version,
+};
+
+pub const HEADLESS_FORMAT: Format = Format::R8G8B8A8_SRGB;
+
+pub struct HeadlessEngine {
+    pub physical_index: usize,
+    pub sample_count: SampleCount,
+    pub dimensions: [u32; 2],
+    pub device: Arc<Device>,
+    pub queue: Arc<Queue>,
+    pub render_pass: Arc<RenderPass>,
+    pub pipelines: Pipelines,
+    pub color: Arc<ImageView<Arc<AttachmentImage>>>,
+    pub scene: RwLock<Arc<Scene>>,
+    pub frame: u64,
+    pub delta: f32,
+    pub shadow_atlas_size: u32,
+    initialized_engine: InitializedEngine,
+}
+
+impl HeadlessEngine {
+    pub fn instance() -> Result<Arc<Instance>, Error> {
+        let instance = Instance::new(None, Version::V1_1, &InstanceExtensions::none(), None)?;
+
+        Ok(instance)
+    }
+
+    pub fn new(
+        physical_index: usize,
+        instance: Arc<Instance>,
+        scene: Arc<Scene>,
+        dimensions: [u32; 2],
+        sample_count: SampleCount,
+    ) -> Result<Self, Error> {
+        let physical = PhysicalDevice::from_index(&instance, physical_index)
+            .ok_or(Error::MissingPhysicalDevice(physical_index))?;
+        let queue_family = physical
+            .queue_families()
+            .find(|&q| q.supports_graphics())
+            .ok_or(Error::MissingQueueFamily)?;
+        let (device, mut queues) = Device::new(
+            physical,
+            physical.supported_features(),
+            &DeviceExtensions::none(),
+            [(queue_family, 0.5)].iter().cloned(),
+        )?;
+        let queue = queues.next().ok_or(Error::MissingQueue)?;
+        let render_pass =
+            Engine::create_render_pass(device.clone(), HEADLESS_FORMAT, sample_count)?;
+        let shaders = Shaders::new(device.clone())?;
+        let pipelines =
+            Engine::create_pipelines(render_pass.clone(), device.clone(), shaders.clone())?;
+        let color = ImageView::new(AttachmentImage::with_usage(
+            device.clone(),
+            dimensions,
+            HEADLESS_FORMAT,
+            ImageUsage {
+                transfer_source: true,
+                ..ImageUsage::none()
+            },
+        )?)?;
+        let initialized_engine =
+            InitializedEngine::from_device(device.clone(), shaders, SHADOW_ATLAS_SIZE)?;
+
+        Ok(Self {
+            physical_index,
+            sample_count,
+            dimensions,
+            device,
+            queue,
+            render_pass,
+            pipelines,
+            color,
+            scene: RwLock::new(scene),
+            frame: 0,
+            delta: 1.0 / 60.0,
+            shadow_atlas_size: SHADOW_ATLAS_SIZE,
+            initialized_engine,
+        })
+    }
+
+    pub fn first(
+        instance: Arc<Instance>,
+        scene: Arc<Scene>,
+        dimensions: [u32; 2],
+        sample_count: SampleCount,
+    ) -> Result<Self, Error> {
+        Self::new(0, instance, scene, dimensions, sample_count)
+    }
+
+    pub fn set_scene(&mut self, scene: Arc<Scene>) {
+        *self.scene.write().unwrap() = scene;
+
+        self.frame = 0;
+    }
+
+    pub fn render_frame(&mut self) -> Result<Frame, Error> {
+        let scene = { self.scene.read().unwrap().clone() };
+
+        if self.frame == 0 {
+            scene.root.on_init();
+        }
+
+        scene.time.write().unwrap().advance(self.delta);
+        scene.schedule().run(&scene.root)?;
+
+        self.initialized_engine.shadow_atlas.size = self.shadow_atlas_size;
+
+        let [width, height] = self.dimensions;
+        let framebuffer = Engine::create_framebuffers(
+            self.device.clone(),
+            HEADLESS_FORMAT,
+            self.render_pass.clone(),
+            self.color.clone(),
+            self.sample_count,
+            &self.dimensions,
+        )?;
+        let buffer = CpuAccessibleBuffer::from_iter(
+            self.device.clone(),
+            BufferUsage::transfer_destination(),
+            false,
+            (0..width * height * 4).map(|_| 0u8),
+        )?;
+        let mut builder = AutoCommandBufferBuilder::primary(
+            self.device.clone(),
+            self.queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+
+        Engine::draw_scene(
+            &mut self.initialized_engine,
+            &scene,
+            &mut builder,
+            &self.pipelines,
+            self.render_pass.clone(),
+            framebuffer,
+            &self.dimensions,
+        )?;
+
+        builder.copy_image_to_buffer(self.color.image().clone(), buffer.clone())?;
+
+        builder
+            .build()?
+            .execute(self.queue.clone())?
+            .then_signal_fence_and_flush()?
+            .wait(None)?;
+
+        self.frame += 1;
+
+        let frame = Frame::new(self.dimensions, buffer.read()?.to_vec());
+
+        Ok(frame)
+    }
+}
@@ -0,0 +1,1131 @@
+use crate::{
+    assets::{Library, Material, Mesh, MeshData, PbrMaterial, Texture, WHITE_TEXTURE},
+    components::{Camera, Light, LightType, Model, Projection, Transform},
+    ecs::{self, Entity},
+    error::Error,
+};
+use cgmath::{InnerSpace, Matrix3, Matrix4, One, Quaternion, Vector2, Vector3, Vector4, Zero};
+use serde::{de::IgnoredAny, Deserialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+pub const DEFAULT_LIGHT_RANGE: f32 = 100.0;
+
+const GLB_MAGIC: &[u8] = b"glTF";
+const GLB_JSON: u32 = 0x4E4F_534A;
+const GLB_BIN: u32 = 0x004E_4942;
+const PNG_MAGIC: &[u8] = b"\x89PNG";
+const TRIANGLES: u32 = 4;
+const ZEROS: [u8; 64] = [0; 64];
+
+fn one() -> f32 {
+    1.0
+}
+
+fn white() -> [f32; 4] {
+    [1.0; 4]
+}
+
+fn white_rgb() -> [f32; 3] {
+    [1.0; 3]
+}
+
+fn triangles() -> u32 {
+    TRIANGLES
+}
+
+fn quarter_pi() -> f32 {
+    std::f32::consts::FRAC_PI_4
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Document {
+    scene: Option<usize>,
+    #[serde(default)]
+    scenes: Vec<SceneNodes>,
+    #[serde(default)]
+    nodes: Vec<Node>,
+    #[serde(default)]
+    meshes: Vec<MeshPrimitives>,
+    #[serde(default)]
+    accessors: Vec<Accessor>,
+    #[serde(default)]
+    buffer_views: Vec<BufferView>,
+    #[serde(default)]
+    buffers: Vec<Buffer>,
+    #[serde(default)]
+    materials: Vec<MaterialInfo>,
+    #[serde(default)]
+    textures: Vec<TextureSource>,
+    #[serde(default)]
+    images: Vec<Image>,
+    #[serde(default)]
+    cameras: Vec<CameraInfo>,
+    #[serde(default)]
+    extensions: DocumentExtensions,
+}
+
+#[derive(Deserialize)]
+struct SceneNodes {
+    #[serde(default)]
+    nodes: Vec<usize>,
+}
+
+#[derive(Deserialize)]
+struct Node {
+    name: Option<String>,
+    #[serde(default)]
+    children: Vec<usize>,
+    matrix: Option<[f32; 16]>,
+    translation: Option<[f32; 3]>,
+    rotation: Option<[f32; 4]>,
+    scale: Option<[f32; 3]>,
+    mesh: Option<usize>,
+    camera: Option<usize>,
+    #[serde(default)]
+    extensions: NodeExtensions,
+}
+
+#[derive(Default, Deserialize)]
+struct NodeExtensions {
+    #[serde(rename = "KHR_lights_punctual")]
+    light: Option<NodeLight>,
+}
+
+#[derive(Deserialize)]
+struct NodeLight {
+    light: usize,
+}
+
+#[derive(Deserialize)]
+struct MeshPrimitives {
+    #[serde(default)]
+    primitives: Vec<Primitive>,
+}
+
+#[derive(Deserialize)]
+struct Primitive {
+    attributes: HashMap<String, usize>,
+    indices: Option<usize>,
+    material: Option<usize>,
+    #[serde(default = "triangles")]
+    mode: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Accessor {
+    buffer_view: Option<usize>,
+    #[serde(default)]
+    byte_offset: usize,
+    component_type: u32,
+    #[serde(default)]
+    normalized: bool,
+    count: usize,
+    #[serde(rename = "type")]
+    kind: String,
+    sparse: Option<IgnoredAny>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BufferView {
+    buffer: usize,
+    #[serde(default)]
+    byte_offset: usize,
+    byte_length: usize,
+    byte_stride: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct Buffer {
+    uri: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MaterialInfo {
+    pbr_metallic_roughness: Option<PbrInfo>,
+    normal_texture: Option<TextureInfo>,
+    occlusion_texture: Option<TextureInfo>,
+    emissive_texture: Option<TextureInfo>,
+    #[serde(default)]
+    emissive_factor: [f32; 3],
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PbrInfo {
+    #[serde(default = "white")]
+    base_color_factor: [f32; 4],
+    base_color_texture: Option<TextureInfo>,
+    #[serde(default = "one")]
+    metallic_factor: f32,
+    #[serde(default = "one")]
+    roughness_factor: f32,
+    metallic_roughness_texture: Option<TextureInfo>,
+}
+
+#[derive(Deserialize)]
+struct TextureInfo {
+    index: usize,
+    #[serde(default = "one")]
+    scale: f32,
+    #[serde(default = "one")]
+    strength: f32,
+}
+
+#[derive(Deserialize)]
+struct TextureSource {
+    source: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Image {
+    uri: Option<String>,
+    buffer_view: Option<usize>,
+    mime_type: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CameraInfo {
+    perspective: Option<Perspective>,
+    orthographic: Option<Orthographic>,
+}
+
+#[derive(Deserialize)]
+struct Perspective {
+    yfov: f32,
+    znear: f32,
+    zfar: Option<f32>,
+}
+
+#[derive(Deserialize)]
+struct Orthographic {
+    ymag: f32,
+    znear: f32,
+    zfar: f32,
+}
+
+#[derive(Default, Deserialize)]
+struct DocumentExtensions {
+    #[serde(rename = "KHR_lights_punctual")]
+    lights: Option<Lights>,
+}
+
+#[derive(Deserialize)]
+struct Lights {
+    #[serde(default)]
+    lights: Vec<LightInfo>,
+}
+
+#[derive(Deserialize)]
+struct LightInfo {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default = "white_rgb")]
+    color: [f32; 3],
+    #[serde(default = "one")]
+    intensity: f32,
+    range: Option<f32>,
+    spot: Option<Spot>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Spot {
+    #[serde(default)]
+    inner_cone_angle: f32,
+    #[serde(default = "quarter_pi")]
+    outer_cone_angle: f32,
+}
+
+fn invalid(message: &str) -> Error {
+    Error::InvalidGltf(message.to_string())
+}
+
+fn decode_base64(data: &str) -> Result<Vec<u8>, Error> {
+    let mut bytes = Vec::with_capacity(data.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for c in data
+        .bytes()
+        .filter(|c| !c.is_ascii_whitespace() && *c != b'=')
+    {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return Err(invalid("invalid base64 data")),
+        };
+
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+
+    Ok(bytes)
+}
+
+fn decode_uri(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn data_uri(uri: &str) -> Option<Result<Vec<u8>, Error>> {
+    let (header, data) = uri.strip_prefix("data:")?.split_once(',')?;
+
+    if header.ends_with(";base64") {
+        Some(decode_base64(data))
+    } else {
+        Some(Ok(decode_uri(data).into_bytes()))
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, Error> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| invalid("truncated binary glTF"))
+}
+
+fn parse_glb(bytes: &[u8]) -> Result<(Vec<u8>, Option<Vec<u8>>), Error> {
+    if read_u32(bytes, 4)? != 2 {
+        return Err(invalid("unsupported binary glTF version"));
+    }
+
+    let length = (read_u32(bytes, 8)? as usize).min(bytes.len());
+    let mut offset = 12;
+    let mut json = None;
+    let mut bin = None;
+
+    while offset + 8 <= length {
+        let chunk_length = read_u32(bytes, offset)? as usize;
+        let chunk_type = read_u32(bytes, offset + 4)?;
+        let chunk = bytes
+            .get(offset + 8..offset + 8 + chunk_length)
+            .ok_or_else(|| invalid("truncated binary glTF chunk"))?;
+
+        match chunk_type {
+            GLB_JSON if json.is_none() => json = Some(chunk.to_vec()),
+            GLB_BIN if bin.is_none() => bin = Some(chunk.to_vec()),
+            _ => {}
+        }
+
+        offset += 8 + chunk_length;
+    }
+
+    Ok((json.ok_or_else(|| invalid("missing JSON chunk"))?, bin))
+}
+
+fn components(kind: &str) -> Result<usize, Error> {
+    match kind {
+        "SCALAR" => Ok(1),
+        "VEC2" => Ok(2),
+        "VEC3" => Ok(3),
+        "VEC4" | "MAT2" => Ok(4),
+        "MAT3" => Ok(9),
+        "MAT4" => Ok(16),
+        _ => Err(invalid("unknown accessor type")),
+    }
+}
+
+fn component_size(component_type: u32) -> Result<usize, Error> {
+    match component_type {
+        5120 | 5121 => Ok(1),
+        5122 | 5123 => Ok(2),
+        5125 | 5126 => Ok(4),
+        _ => Err(invalid("unknown accessor component type")),
+    }
+}
+
+fn component(bytes: &[u8], component_type: u32, normalized: bool) -> f32 {
+    let (value, max) = match component_type {
+        5120 => (bytes[0] as i8 as f32, 127.0),
+        5121 => (bytes[0] as f32, 255.0),
+        5122 => (i16::from_le_bytes([bytes[0], bytes[1]]) as f32, 32767.0),
+        5123 => (u16::from_le_bytes([bytes[0], bytes[1]]) as f32, 65535.0),
+        5125 => (
+            u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32,
+            1.0,
+        ),
+        _ => return f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+    };
+
+    if normalized {
+        (value / max).max(-1.0)
+    } else {
+        value
+    }
+}
+
+fn decompose(matrix: [f32; 16]) -> (Vector3<f32>, Quaternion<f32>, Vector3<f32>) {
+    let m = *<&Matrix4<f32>>::from(&matrix);
+    let mut axes = [m.x.truncate(), m.y.truncate(), m.z.truncate()];
+    let mut scale = axes.map(|axis| axis.magnitude());
+
+    // A mirroring matrix has no rotation, so fold the reflection into the x scale.
+    if axes[0].cross(axes[1]).dot(axes[2]) < 0.0 {
+        axes[0] = -axes[0];
+        scale[0] = -scale[0];
+    }
+
+    let [x, y, z] = [0, 1, 2].map(|i| {
+        if scale[i] != 0.0 {
+            axes[i] / scale[i].abs()
+        } else {
+            axes[i]
+        }
+    });
+
+    (
+        m.w.truncate(),
+        Quaternion::from(Matrix3::from_cols(x, y, z)),
+        Vector3::from(scale),
+    )
+}
+
+// Nodes are imported recursively, so a child list that loops back on itself has to be caught
+// before it overflows the stack.
+fn check_hierarchy(nodes: &[Node]) -> Result<(), Error> {
+    let mut done = HashSet::new();
+
+    for start in 0..nodes.len() {
+        let mut path = HashSet::new();
+        let mut stack = vec![(start, false)];
+
+        while let Some((index, leaving)) = stack.pop() {
+            if leaving {
+                path.remove(&index);
+                done.insert(index);
+                continue;
+            }
+
+            if done.contains(&index) {
+                continue;
+            }
+
+            if !path.insert(index) {
+                return Err(invalid("cyclic node hierarchy"));
+            }
+
+            let node = nodes.get(index).ok_or_else(|| invalid("missing node"))?;
+
+            stack.push((index, true));
+            stack.extend(node.children.iter().map(|&child| (child, false)));
+        }
+    }
+
+    Ok(())
+}
+
+fn index(bytes: &[u8], component_type: u32) -> u32 {
+    match component_type {
+        5121 => bytes[0] as u32,
+        5123 => u16::from_le_bytes([bytes[0], bytes[1]]) as u32,
+        _ => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+    }
+}
+
+struct Importer<'a> {
+    library: &'a Library,
+    path: &'a str,
+    directory: PathBuf,
+    document: Document,
+    buffers: Vec<Vec<u8>>,
+    // Keyed by texture index and whether it holds data rather than color.
+    textures: HashMap<(usize, bool), Arc<Texture>>,
+    materials: HashMap<Option<usize>, Arc<Material>>,
+    meshes: HashMap<(usize, usize), Option<Arc<Mesh>>>,
+}
+
+impl<'a> Importer<'a> {
+    fn new(library: &'a Library, path: &'a str) -> Result<Self, Error> {
+        let directory = Path::new(path)
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        let bytes = fs::read(library.root.join(path))?;
+        let (json, bin) = if bytes.starts_with(GLB_MAGIC) {
+            parse_glb(&bytes)?
+        } else {
+            (bytes, None)
+        };
+        let document = serde_json::from_slice::<Document>(&json)?;
+
+        check_hierarchy(&document.nodes)?;
+
+        let mut bin = bin;
+        let mut buffers = Vec::new();
+
+        for buffer in &document.buffers {
+            let data = match &buffer.uri {
+                Some(uri) => match data_uri(uri) {
+                    Some(data) => data?,
+                    None => fs::read(library.root.join(&directory).join(decode_uri(uri)))?,
+                },
+                None => bin
+                    .take()
+                    .ok_or_else(|| invalid("buffer without uri or binary chunk"))?,
+            };
+
+            buffers.push(data);
+        }
+
+        Ok(Self {
+            library,
+            path,
+            directory,
+            document,
+            buffers,
+            textures: HashMap::new(),
+            materials: HashMap::new(),
+            meshes: HashMap::new(),
+        })
+    }
+
+    fn view(&self, index: usize) -> Result<(&[u8], Option<usize>), Error> {
+        let view = self
+            .document
+            .buffer_views
+            .get(index)
+            .ok_or_else(|| invalid("missing buffer view"))?;
+        let data = self
+            .buffers
+            .get(view.buffer)
+            .and_then(|buffer| buffer.get(view.byte_offset..view.byte_offset + view.byte_length))
+            .ok_or_else(|| invalid("buffer view out of range"))?;
+
+        Ok((data, view.byte_stride))
+    }
+
+    fn elements(&self, index: usize) -> Result<(&Accessor, Vec<&[u8]>), Error> {
+        let accessor = self
+            .document
+            .accessors
+            .get(index)
+            .ok_or_else(|| invalid("missing accessor"))?;
+        let size = component_size(accessor.component_type)?;
+        let element = size * components(&accessor.kind)?;
+
+        if accessor.sparse.is_some() {
+            return Err(invalid("sparse accessors are not supported"));
+        }
+
+        // Accessors without a buffer view are all zeros.
+        let (data, stride) = match accessor.buffer_view {
+            Some(view) => self.view(view)?,
+            None => {
+                let zeros = ZEROS
+                    .get(..element)
+                    .ok_or_else(|| invalid("accessor element too large"))?;
+
+                return Ok((accessor, vec![zeros; accessor.count]));
+            }
+        };
+        let stride = stride.unwrap_or(element);
+        let elements = (0..accessor.count)
+            .map(|i| {
+                let start = accessor.byte_offset + i * stride;
+
+                data.get(start..start + element)
+                    .ok_or_else(|| invalid("accessor out of range"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok((accessor, elements))
+    }
+
+    fn read(&self, index: usize) -> Result<Vec<Vec<f32>>, Error> {
+        let (accessor, elements) = self.elements(index)?;
+        let size = component_size(accessor.component_type)?;
+
+        Ok(elements
+            .into_iter()
+            .map(|element| {
+                element
+                    .chunks_exact(size)
+                    .map(|bytes| component(bytes, accessor.component_type, accessor.normalized))
+                    .collect()
+            })
+            .collect())
+    }
+
+    fn read_indices(&self, index: usize) -> Result<Vec<u32>, Error> {
+        let (accessor, elements) = self.elements(index)?;
+
+        Ok(elements
+            .into_iter()
+            .map(|element| self::index(element, accessor.component_type))
+            .collect())
+    }
+
+    fn texture(&mut self, index: usize, data: bool) -> Result<Arc<Texture>, Error> {
+        if let Some(texture) = self.textures.get(&(index, data)) {
+            return Ok(texture.clone());
+        }
+
+        let image_index = self
+            .document
+            .textures
+            .get(index)
+            .and_then(|texture| texture.source)
+            .ok_or_else(|| invalid("texture without source"))?;
+        let image = self
+            .document
+            .images
+            .get(image_index)
+            .ok_or_else(|| invalid("missing image"))?;
+        let texture = match (&image.uri, image.buffer_view) {
+            (Some(uri), _) if !uri.starts_with("data:") => {
+                let path = self.directory.join(decode_uri(uri));
+
+                // Only PNG can be decoded; anything else fails the import rather than
+                // quietly losing its textures.
+                if path.extension().and_then(|e| e.to_str()) != Some("png") {
+                    return Err(Error::UnsupportedFormat(uri.clone()));
+                }
+
+                if data {
+                    self.library.data_texture(&path.to_string_lossy())?
+                } else {
+                    self.library.texture(&path.to_string_lossy())?
+                }
+            }
+
+            (uri, view) => {
+                let bytes = match (uri, view) {
+                    (Some(uri), _) => {
+                        data_uri(uri).ok_or_else(|| invalid("invalid data uri"))??
+                    }
+                    (None, Some(view)) => self.view(view)?.0.to_vec(),
+                    (None, None) => return Err(invalid("image without data")),
+                };
+
+                if !bytes.starts_with(PNG_MAGIC) {
+                    return Err(Error::UnsupportedFormat(
+                        image.mime_type.clone().unwrap_or_default(),
+                    ));
+                }
+
+                let path = format!("{}#image{}", self.path, image_index);
+                let format = if data {
+                    self.library.data_format()
+                } else {
+                    self.library.format
+                };
+                let texture = Texture::from_png(
+                    Cursor::new(bytes),
+                    self.library.queue.clone(),
+                    self.library.sampler.clone(),
+                    format,
+                )?;
+
+                if data {
+                    self.library.insert_data_texture(&path, texture.clone());
+                } else {
+                    self.library.insert_texture(&path, texture.clone());
+                }
+
+                texture
+            }
+        };
+
+        self.textures.insert((index, data), texture.clone());
+
+        Ok(texture)
+    }
+
+    fn material(&mut self, index: Option<usize>) -> Result<Arc<Material>, Error> {
+        if let Some(material) = self.materials.get(&index) {
+            return Ok(material.clone());
+        }
+
+        let material = self.build_material(index)?;
+
+        self.materials.insert(index, material.clone());
+
+        Ok(material)
+    }
+
+    fn build_material(&mut self, index: Option<usize>) -> Result<Arc<Material>, Error> {
+        let info = match index.and_then(|index| self.document.materials.get(index)) {
+            Some(info) => info,
+            None => return Ok(Material::pbr(0.03, PbrMaterial::default())),
+        };
+        let (base_color, base_color_texture, metallic, roughness, metallic_roughness) =
+            match &info.pbr_metallic_roughness {
+                Some(pbr) => (
+                    pbr.base_color_factor,
+                    pbr.base_color_texture.as_ref().map(|t| t.index),
+                    pbr.metallic_factor,
+                    pbr.roughness_factor,
+                    pbr.metallic_roughness_texture.as_ref().map(|t| t.index),
+                ),
+                None => (white(), None, 1.0, 1.0, None),
+            };
+        let normal = info.normal_texture.as_ref().map(|t| (t.index, t.scale));
+        let occlusion = info
+            .occlusion_texture
+            .as_ref()
+            .map(|t| (t.index, t.strength));
+        let emissive = info.emissive_factor;
+        let emissive_texture = info.emissive_texture.as_ref().map(|t| t.index);
+        let mut pbr = PbrMaterial::new(Vector4::from(base_color), metallic, roughness);
+
+        pbr.base_color_texture = base_color_texture
+            .map(|index| self.texture(index, false))
+            .transpose()?;
+        pbr.metallic_texture = metallic_roughness
+            .map(|index| self.texture(index, true))
+            .transpose()?;
+        pbr.roughness_texture = pbr.metallic_texture.clone();
+        pbr.emissive = Vector3::from(emissive);
+        pbr.emissive_texture = emissive_texture
+            .map(|index| self.texture(index, false))
+            .transpose()?;
+
+        if let Some((index, strength)) = occlusion {
+            pbr.occlusion_texture = Some(self.texture(index, true)?);
+            pbr.occlusion_strength = strength;
+        }
+
+        let (normal_scale, normal_texture) = match normal {
+            Some((index, scale)) => (scale, Some(self.texture(index, true)?)),
+            None => (1.0, None),
+        };
+
+        Ok(Arc::new(Material {
+            ambient: 0.03,
+            diff_strength: 1.0,
+            spec_strength: 0.0,
+            spec_power: 1,
+            normal_scale,
+            normal_texture,
+            pbr: Some(pbr),
+        }))
+    }
+
+    fn mesh(&self, primitive: &Primitive) -> Result<Option<Arc<Mesh>>, Error> {
+        if primitive.mode != TRIANGLES {
+            return Ok(None);
+        }
+
+        let mut data = match primitive.attributes.get("POSITION") {
+            Some(&accessor) => MeshData::new(
+                self.read(accessor)?
+                    .into_iter()
+                    .map(|p| Vector3::new(p[0], p[1], p[2]))
+                    .collect(),
+                Vec::new(),
+            ),
+            None => return Ok(None),
+        };
+
+        data.indices = match primitive.indices {
+            Some(accessor) => self.read_indices(accessor)?,
+            None => (0..data.positions.len() as u32).collect(),
+        };
+
+        if data
+            .indices
+            .iter()
+            .any(|&i| i as usize >= data.positions.len())
+        {
+            return Err(invalid("index out of range"));
+        }
+
+        if let Some(&accessor) = primitive.attributes.get("TEXCOORD_0") {
+            data.uvs = self
+                .read(accessor)?
+                .into_iter()
+                .map(|uv| Vector2::new(uv[0], uv[1]))
+                .collect();
+        }
+
+        if let Some(&accessor) = primitive.attributes.get("NORMAL") {
+            data.normals = self
+                .read(accessor)?
+                .into_iter()
+                .map(|n| Vector3::new(n[0], n[1], n[2]))
+                .collect();
+        }
+
+        if let Some(&accessor) = primitive.attributes.get("TANGENT") {
+            data.tangents = self
+                .read(accessor)?
+                .into_iter()
+                .map(|t| Vector4::new(t[0], t[1], t[2], t[3]))
+                .collect();
+        }
+
+        if let Some(&accessor) = primitive.attributes.get("COLOR_0") {
+            data.colors = self
+                .read(accessor)?
+                .into_iter()
+                .map(|c| Vector4::new(c[0], c[1], c[2], c.get(3).copied().unwrap_or(1.0)))
+                .collect();
+        }
+
+        Ok(Some(Mesh::new(self.library.queue.clone(), data)?))
+    }
+
+    fn transform(&self, id: &str, node: &Node) -> Arc<Transform> {
+        let (position, rotation, scale) = match node.matrix {
+            Some(matrix) => decompose(matrix),
+            None => (
+                node.translation
+                    .map(Vector3::from)
+                    .unwrap_or_else(Vector3::zero),
+                node.rotation
+                    .map(Quaternion::from)
+                    .unwrap_or_else(Quaternion::one),
+                node.scale
+                    .map(Vector3::from)
+                    .unwrap_or_else(|| Vector3::new(1.0, 1.0, 1.0)),
+            ),
+        };
+
+        Transform::new(
+            ecs::id(&format!("{}:transform", id)),
+            position,
+            rotation,
+            scale,
+        )
+    }
+
+    fn camera(&self, id: &str, index: usize) -> Result<Arc<Camera>, Error> {
+        let camera = self
+            .document
+            .cameras
+            .get(index)
+            .ok_or_else(|| invalid("missing camera"))?;
+        let projection = match (&camera.perspective, &camera.orthographic) {
+            (Some(perspective), _) => match perspective.zfar {
+                Some(far) => Projection::Perspective {
+                    fov: perspective.yfov,
+                    near: perspective.znear,
+                    far,
+                },
+                None => Projection::ReverseZ {
+                    fov: perspective.yfov,
+                    near: perspective.znear,
+                },
+            },
+
+            (None, Some(orthographic)) => Projection::Orthographic {
+                size: orthographic.ymag,
+                near: orthographic.znear,
+                far: orthographic.zfar,
+            },
+
+            (None, None) => return Err(invalid("camera without projection")),
+        };
+
+        Ok(Camera::with_projection(
+            ecs::id(&format!("{}:camera", id)),
+            projection,
+        ))
+    }
+
+    fn light(&self, id: &str, index: usize) -> Result<Arc<Light>, Error> {
+        let light = self
+            .document
+            .extensions
+            .lights
+            .as_ref()
+            .and_then(|lights| lights.lights.get(index))
+            .ok_or_else(|| invalid("missing light"))?;
+        let range = light.range.unwrap_or(DEFAULT_LIGHT_RANGE);
+        let light_type = match (light.kind.as_str(), &light.spot) {
+            ("directional", _) => LightType::Directional,
+            ("point", _) => LightType::Point { range },
+            ("spot", spot) => LightType::Spot {
+                range,
+                inner_angle: spot.as_ref().map(|s| s.inner_cone_angle).unwrap_or(0.0),
+                outer_angle: spot
+                    .as_ref()
+                    .map(|s| s.outer_cone_angle)
+                    .unwrap_or_else(quarter_pi),
+            },
+            _ => return Err(invalid("unknown light type")),
+        };
+
+        Ok(Light::new(
+            ecs::id(&format!("{}:light", id)),
+            light_type,
+            Vector3::from(light.color),
+            light.intensity,
+        ))
+    }
+
+    fn node(&mut self, index: usize) -> Result<Arc<Entity>, Error> {
+        let id = format!("{}#node{}", self.path, index);
+        let node = self
+            .document
+            .nodes
+            .get(index)
+            .ok_or_else(|| invalid("missing node"))?;
+        let entity = Entity::new(ecs::id(node.name.as_deref().unwrap_or(&id)));
+        let children = node.children.clone();
+        let mesh = node.mesh;
+        let camera = node.camera;
+        let light = node.extensions.light.as_ref().map(|light| light.light);
+
+        entity.add(&self.transform(&id, node));
+
+        if let Some(camera) = camera {
+            entity.add(&self.camera(&id, camera)?);
+        }
+
+        if let Some(light) = light {
+            entity.add(&self.light(&id, light)?);
+        }
+
+        if let Some(mesh) = mesh {
+            let primitives = self
+                .document
+                .meshes
+                .get(mesh)
+                .ok_or_else(|| invalid("missing mesh"))?
+                .primitives
+                .len();
+
+            for primitive in 0..primitives {
+                let key = format!("{}#mesh{}/{}", self.path, mesh, primitive);
+                let material = self.document.meshes[mesh].primitives[primitive].material;
+                let gpu_mesh = match self.meshes.get(&(mesh, primitive)) {
+                    Some(gpu_mesh) => gpu_mesh.clone(),
+                    None => {
+                        let gpu_mesh =
+                            self.mesh(&self.document.meshes[mesh].primitives[primitive])?;
+
+                        if let Some(gpu_mesh) = &gpu_mesh {
+                            self.library.insert_mesh(&key, gpu_mesh.clone());
+                        }
+
+                        self.meshes.insert((mesh, primitive), gpu_mesh.clone());
+
+                        gpu_mesh
+                    }
+                };
+                let gpu_mesh = match gpu_mesh {
+                    Some(gpu_mesh) => gpu_mesh,
+                    None => continue,
+                };
+                let model = Model::new(
+                    ecs::id(&key),
+                    gpu_mesh,
+                    self.library.texture(WHITE_TEXTURE)?,
+                    self.material(material)?,
+                    Vector4::new(1.0, 1.0, 1.0, 1.0),
+                    true,
+                    true,
+                );
+
+                if primitives == 1 {
+                    entity.add(&model);
+                } else {
+                    let child = Entity::new(ecs::id(&key));
+
+                    child.add(&Transform::new(
+                        ecs::id(&format!("{}:transform", key)),
+                        Vector3::zero(),
+                        Quaternion::one(),
+                        Vector3::new(1.0, 1.0, 1.0),
+                    ));
+                    child.add(&model);
+                    entity.add(&child);
+                }
+            }
+        }
+
+        for child in children {
+            entity.add(&self.node(child)?);
+        }
+
+        Ok(entity)
+    }
+
+    fn roots(&self) -> Vec<usize> {
+        let scene = self
+            .document
+            .scene
+            .and_then(|scene| self.document.scenes.get(scene))
+            .or_else(|| self.document.scenes.first());
+
+        match scene {
+            Some(scene) => scene.nodes.clone(),
+            None => (0..self.document.nodes.len())
+                .filter(|&i| {
+                    !self
+                        .document
+                        .nodes
+                        .iter()
+                        .any(|node| node.children.contains(&i))
+                })
+                .collect(),
+        }
+    }
+}
+
+pub fn load_gltf(library: &Library, path: &str) -> Result<Arc<Entity>, Error> {
+    let mut importer = Importer::new(library, path)?;
+    let root = Entity::new(ecs::id(path));
+
+    root.add(&Transform::new(
+        ecs::id(&format!("{}:transform", path)),
+        Vector3::zero(),
+        Quaternion::one(),
+        Vector3::new(1.0, 1.0, 1.0),
+    ));
+
+    for node in importer.roots() {
+        root.add(&importer.node(node)?);
+    }
+
+    Ok(root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{Deg, Rotation3};
+
+    fn glb(chunks: &[(u32, &[u8])]) -> Vec<u8> {
+        let mut bytes = GLB_MAGIC.to_vec();
+        let length = 12 + chunks.iter().map(|(_, c)| 8 + c.len()).sum::<usize>();
+
+        bytes.extend_from_slice(&2u32.to_le_bytes());
+        bytes.extend_from_slice(&(length as u32).to_le_bytes());
+
+        for (kind, chunk) in chunks {
+            bytes.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(&kind.to_le_bytes());
+            bytes.extend_from_slice(chunk);
+        }
+
+        bytes
+    }
+
+    fn nodes(json: &str) -> Vec<Node> {
+        serde_json::from_str::<Document>(json).unwrap().nodes
+    }
+
+    #[test]
+    fn base64_decodes_with_and_without_padding() {
+        assert_eq!(decode_base64("aGVsbG8=").unwrap(), b"hello");
+        assert_eq!(decode_base64("aGVsbG8").unwrap(), b"hello");
+        assert_eq!(decode_base64("+/8-_w==").unwrap(), [0xfb, 0xff, 0x3e, 0xff]);
+        assert!(decode_base64("a*b").is_err());
+    }
+
+    #[test]
+    fn data_uris_are_decoded() {
+        let data = |uri| data_uri(uri).unwrap().unwrap();
+
+        assert_eq!(data("data:application/octet-stream;base64,AAEC"), [0, 1, 2]);
+        assert_eq!(data("data:text/plain,a%20b"), b"a b");
+        assert!(data_uri("buffer.bin").is_none());
+    }
+
+    #[test]
+    fn glb_chunks_are_split() {
+        let (json, bin) =
+            parse_glb(&glb(&[(GLB_JSON, b"{}  "), (GLB_BIN, &[1, 2, 3, 4])])).unwrap();
+
+        assert_eq!(json, b"{}  ");
+        assert_eq!(bin.unwrap(), [1, 2, 3, 4]);
+
+        let (_, bin) = parse_glb(&glb(&[(GLB_JSON, b"{}  ")])).unwrap();
+
+        assert!(bin.is_none());
+        assert!(parse_glb(&glb(&[(GLB_BIN, &[0; 4])])).is_err());
+
+        let mut truncated = glb(&[(GLB_JSON, b"{}  ")]);
+
+        truncated.truncate(truncated.len() - 2);
+        truncated[8..12].copy_from_slice(&100u32.to_le_bytes());
+        assert!(parse_glb(&truncated).is_err());
+    }
+
+    #[test]
+    fn normalized_components_map_to_unit_range() {
+        assert_eq!(component(&[255], 5121, true), 1.0);
+        assert_eq!(component(&[255], 5121, false), 255.0);
+        assert_eq!(component(&[0x80], 5120, true), -1.0);
+        assert_eq!(component(&[0x7f], 5120, true), 1.0);
+        assert_eq!(component(&0x8000u16.to_le_bytes(), 5122, true), -1.0);
+        assert_eq!(component(&u16::MAX.to_le_bytes(), 5123, true), 1.0);
+        assert_eq!(component(&1.5f32.to_le_bytes(), 5126, true), 1.5);
+    }
+
+    #[test]
+    fn matrices_decompose_into_translation_rotation_and_scale() {
+        let rotation = Quaternion::from_angle_y(Deg(90.0));
+        let matrix = Matrix4::from_translation(Vector3::new(1.0, 2.0, 3.0))
+            * Matrix4::from(rotation)
+            * Matrix4::from_nonuniform_scale(2.0, 3.0, 4.0);
+        let (position, decomposed, scale) = decompose(*AsRef::<[f32; 16]>::as_ref(&matrix));
+
+        assert!((position - Vector3::new(1.0, 2.0, 3.0)).magnitude() < 1e-5);
+        assert!(decomposed.dot(rotation).abs() > 1.0 - 1e-5);
+        assert!((scale - Vector3::new(2.0, 3.0, 4.0)).magnitude() < 1e-5);
+
+        let mirrored = Matrix4::from_nonuniform_scale(-1.0, 1.0, 1.0);
+        let (_, rotation, scale) = decompose(*AsRef::<[f32; 16]>::as_ref(&mirrored));
+
+        assert_eq!(scale, Vector3::new(-1.0, 1.0, 1.0));
+        assert!(rotation.dot(Quaternion::one()).abs() > 1.0 - 1e-5);
+    }
+
+    #[test]
+    fn cyclic_hierarchies_are_rejected() {
+        assert!(check_hierarchy(&nodes(
+            r#"{"nodes": [{"children": [1, 2]}, {"children": [2]}, {}]}"#
+        ))
+        .is_ok());
+        assert!(check_hierarchy(&nodes(
+            r#"{"nodes": [{"children": [1]}, {"children": [0]}]}"#
+        ))
+        .is_err());
+        assert!(check_hierarchy(&nodes(r#"{"nodes": [{"children": [0]}]}"#)).is_err());
+        assert!(check_hierarchy(&nodes(r#"{"nodes": [{"children": [3]}]}"#)).is_err());
+    }
+}
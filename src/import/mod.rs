@@ -0,0 +1,16 @@
+pub mod gltf;
+pub mod wavefront;
+
+pub use gltf::load_gltf;
+pub use wavefront::load_obj;
+
+use crate::{assets::Library, ecs::Entity, error::Error};
+use std::{path::Path, sync::Arc};
+
+pub fn load(library: &Library, path: &str) -> Result<Arc<Entity>, Error> {
+    match Path::new(path).extension().and_then(|e| e.to_str()) {
+        Some("obj") => load_obj(library, path),
+        Some("gltf") | Some("glb") => load_gltf(library, path),
+        _ => Err(Error::UnsupportedFormat(path.to_string())),
+    }
+}
@@ -0,0 +1,350 @@
+use crate::{
+    assets::{Library, Material, Mesh, MeshData, Texture, WHITE_TEXTURE},
+    components::{Model, Transform},
+    ecs::{self, Entity},
+    error::Error,
+};
+use cgmath::{One, Quaternion, Vector2, Vector3, Vector4, Zero};
+use obj::raw::{
+    material::{Material as MtlMaterial, MtlColor},
+    object::{Polygon, Range},
+    parse_mtl, parse_obj, RawObj,
+};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::BufReader,
+    path::Path,
+    sync::Arc,
+};
+
+pub const DEFAULT_GROUP: &str = "default";
+
+struct MtlAssets {
+    material: Arc<Material>,
+    texture: Arc<Texture>,
+    color: Vector4<f32>,
+}
+
+fn corners(polygon: &Polygon) -> Vec<(usize, Option<usize>, Option<usize>)> {
+    match polygon {
+        Polygon::P(corners) => corners.iter().map(|&p| (p, None, None)).collect(),
+        Polygon::PT(corners) => corners.iter().map(|&(p, t)| (p, Some(t), None)).collect(),
+        Polygon::PN(corners) => corners.iter().map(|&(p, n)| (p, None, Some(n))).collect(),
+        Polygon::PTN(corners) => corners
+            .iter()
+            .map(|&(p, t, n)| (p, Some(t), Some(n)))
+            .collect(),
+    }
+}
+
+fn mesh_data(obj: &RawObj, ranges: &[Range]) -> MeshData {
+    let mut lookup = HashMap::new();
+    let mut data = MeshData::default();
+    let mut generated = Vec::new();
+
+    for range in ranges {
+        for polygon in &obj.polygons[range.start..range.end] {
+            let corners = corners(polygon);
+            let face = corners
+                .iter()
+                .map(|&key| {
+                    *lookup.entry(key).or_insert_with(|| {
+                        let (p, t, n) = key;
+                        let position = obj.positions[p];
+                        let uv = t.map(|t| obj.tex_coords[t]).unwrap_or((0.0, 0.0, 0.0));
+
+                        data.positions
+                            .push(Vector3::new(position.0, position.1, position.2));
+                        data.uvs.push(Vector2::new(uv.0, uv.1));
+                        data.normals.push(
+                            n.map(|n| {
+                                Vector3::new(obj.normals[n].0, obj.normals[n].1, obj.normals[n].2)
+                            })
+                            .unwrap_or_else(Vector3::zero),
+                        );
+                        generated.push(n.is_none());
+
+                        data.positions.len() as u32 - 1
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            for i in 1..face.len().saturating_sub(1) {
+                data.indices
+                    .extend_from_slice(&[face[0], face[i], face[i + 1]]);
+            }
+        }
+    }
+
+    if generated.contains(&true) {
+        let authored = data.normals.clone();
+
+        data.compute_smooth_normals();
+
+        for (i, normal) in authored.into_iter().enumerate() {
+            if !generated[i] {
+                data.normals[i] = normal;
+            }
+        }
+    }
+
+    data
+}
+
+fn rgb(color: &Option<MtlColor>) -> Option<Vector3<f32>> {
+    match color {
+        Some(MtlColor::Rgb(r, g, b)) => Some(Vector3::new(*r, *g, *b)),
+        _ => None,
+    }
+}
+
+fn average(color: Vector3<f32>) -> f32 {
+    (color.x + color.y + color.z) / 3.0
+}
+
+struct NormalMap {
+    file: String,
+    scale: f32,
+}
+
+// A plain `bump` map is a height map, which we can't shade with, so only `norm` statements and
+// `bump -bm <scale>` (as Blender exports normal maps) become normal maps. obj-rs rejects both
+// forms, so they are taken out of the source before it gets to parse it.
+fn normal_maps(source: &str) -> (String, HashMap<String, NormalMap>) {
+    let mut remaining = String::with_capacity(source.len());
+    let mut maps = HashMap::new();
+    let mut name = None;
+
+    for line in source.lines() {
+        let args = line.split_whitespace().collect::<Vec<_>>();
+
+        match args[..] {
+            ["newmtl", material] => name = Some(material.to_string()),
+            ["norm", .., file] | ["bump" | "map_bump" | "map_Bump", "-bm", _, .., file] => {
+                let scale = args
+                    .iter()
+                    .position(|&arg| arg == "-bm")
+                    .and_then(|i| args.get(i + 1)?.parse().ok())
+                    .unwrap_or(1.0);
+
+                if let Some(name) = &name {
+                    maps.insert(
+                        name.clone(),
+                        NormalMap {
+                            file: file.to_string(),
+                            scale,
+                        },
+                    );
+                }
+
+                continue;
+            }
+            _ => {}
+        }
+
+        remaining.push_str(line);
+        remaining.push('\n');
+    }
+
+    (remaining, maps)
+}
+
+fn material(
+    mtl: Option<&MtlMaterial>,
+    normal_map: Option<&NormalMap>,
+    library: &Library,
+    directory: &Path,
+) -> Result<MtlAssets, Error> {
+    let mtl = match mtl {
+        Some(mtl) => mtl,
+        None => {
+            return Ok(MtlAssets {
+                material: Material::new(0.1, 1.0, 0.0, 1),
+                texture: library.texture(WHITE_TEXTURE)?,
+                color: Vector4::new(1.0, 1.0, 1.0, 1.0),
+            })
+        }
+    };
+    let ambient = rgb(&mtl.ambient).map(average).unwrap_or(0.1);
+    let diffuse = rgb(&mtl.diffuse).unwrap_or_else(|| Vector3::new(1.0, 1.0, 1.0));
+    let specular = rgb(&mtl.specular).map(average).unwrap_or(0.0);
+    let power = mtl.specular_exponent.unwrap_or(1.0).round().max(1.0) as u32;
+    let texture = match &mtl.diffuse_map {
+        Some(map) => library.texture(&directory.join(&map.file).to_string_lossy())?,
+        None => library.texture(WHITE_TEXTURE)?,
+    };
+
+    let material = match normal_map {
+        Some(map) => Material::with_normal_map(
+            ambient,
+            1.0,
+            specular,
+            power,
+            library.data_texture(&directory.join(&map.file).to_string_lossy())?,
+            map.scale,
+        ),
+        None => Material::new(ambient, 1.0, specular, power),
+    };
+
+    Ok(MtlAssets {
+        material,
+        texture,
+        color: diffuse.extend(mtl.dissolve.unwrap_or(1.0)),
+    })
+}
+
+// Meshes come from `usemtl`. Polygons ahead of the first one land in an unnamed mesh, or in
+// none at all, so both end up in the default group.
+fn groups(obj: &RawObj) -> Vec<(String, Vec<Range>)> {
+    let mut groups: Vec<(String, Vec<Range>)> = Vec::new();
+    let mut covered = vec![false; obj.polygons.len()];
+    let mut rest: Vec<Range> = Vec::new();
+
+    for (name, group) in &obj.meshes {
+        let name = if name.is_empty() { DEFAULT_GROUP } else { name };
+
+        match groups.iter_mut().find(|(n, _)| n == name) {
+            Some((_, ranges)) => ranges.extend(group.polygons.iter().copied()),
+            None => groups.push((name.to_string(), group.polygons.clone())),
+        }
+    }
+
+    for (_, ranges) in &groups {
+        for range in ranges {
+            covered[range.start..range.end].fill(true);
+        }
+    }
+
+    for (i, _) in covered.iter().enumerate().filter(|(_, covered)| !**covered) {
+        match rest.last_mut() {
+            Some(range) if range.end == i => range.end = i + 1,
+            _ => rest.push(Range {
+                start: i,
+                end: i + 1,
+            }),
+        }
+    }
+
+    if !rest.is_empty() {
+        match groups.iter_mut().find(|(name, _)| name == DEFAULT_GROUP) {
+            Some((_, ranges)) => ranges.extend(rest),
+            None => groups.push((DEFAULT_GROUP.to_string(), rest)),
+        }
+    }
+
+    groups.sort_by(|a, b| a.0.cmp(&b.0));
+    groups
+}
+
+pub fn load_obj(library: &Library, path: &str) -> Result<Arc<Entity>, Error> {
+    let directory = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+    let obj = parse_obj(BufReader::new(File::open(library.root.join(path))?))?;
+    let mut materials = HashMap::new();
+    let mut normal_maps = HashMap::new();
+
+    for mtl_path in &obj.material_libraries {
+        let source = fs::read_to_string(library.root.join(directory).join(mtl_path))?;
+        let (source, maps) = self::normal_maps(&source);
+
+        materials.extend(parse_mtl(source.as_bytes())?.materials);
+        normal_maps.extend(maps);
+    }
+
+    let root = Entity::new(ecs::id(path));
+
+    root.add(&Transform::new(
+        ecs::id(&format!("{}:transform", path)),
+        Vector3::zero(),
+        Quaternion::one(),
+        Vector3::new(1.0, 1.0, 1.0),
+    ));
+
+    for (name, ranges) in groups(&obj) {
+        let data = mesh_data(&obj, &ranges);
+
+        if data.indices.is_empty() {
+            continue;
+        }
+
+        let key = format!("{}#{}", path, name);
+        let mesh = Mesh::new(library.queue.clone(), data)?;
+        let assets = material(
+            materials.get(&name),
+            normal_maps.get(&name),
+            library,
+            directory,
+        )?;
+        let entity = Entity::new(ecs::id(&name));
+
+        library.insert_mesh(&key, mesh.clone());
+
+        entity.add(&Transform::new(
+            ecs::id(&format!("{}:transform", key)),
+            Vector3::zero(),
+            Quaternion::one(),
+            Vector3::new(1.0, 1.0, 1.0),
+        ));
+        entity.add(&Model::new(
+            ecs::id(&key),
+            mesh,
+            assets.texture,
+            assets.material,
+            assets.color,
+            true,
+            true,
+        ));
+        root.add(&entity);
+    }
+
+    Ok(root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_explicit_normal_maps_are_taken_out() {
+        let (source, maps) = normal_maps(
+            "newmtl a\nKd 1 1 1\nbump height.png\nnewmtl b\nmap_Bump -bm 0.5 b.png\nnewmtl c\nnorm c.png\n",
+        );
+
+        assert_eq!(
+            source,
+            "newmtl a\nKd 1 1 1\nbump height.png\nnewmtl b\nnewmtl c\n"
+        );
+        assert!(!maps.contains_key("a"));
+        assert_eq!(maps["b"].file, "b.png");
+        assert_eq!(maps["b"].scale, 0.5);
+        assert_eq!(maps["c"].file, "c.png");
+        assert_eq!(maps["c"].scale, 1.0);
+        assert!(parse_mtl(source.as_bytes()).is_ok());
+    }
+
+    #[test]
+    fn polygons_before_the_first_material_are_kept() {
+        let obj = parse_obj(
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\nusemtl red\nf 1 2 3\nusemtl blue\nf 1 2 3\n"
+                .as_bytes(),
+        )
+        .unwrap();
+        let groups = groups(&obj)
+            .into_iter()
+            .map(|(name, ranges)| {
+                let count = ranges.iter().map(|r| r.end - r.start).sum::<usize>();
+
+                (name, count)
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            groups,
+            vec![
+                ("blue".to_string(), 1),
+                (DEFAULT_GROUP.to_string(), 1),
+                ("red".to_string(), 1),
+            ]
+        );
+    }
+}
@@ -1,9 +1,19 @@
 pub mod assets;
+pub mod bounds;
+pub mod capture;
 pub mod components;
 pub mod engine;
 pub mod error;
+pub mod golden;
+pub mod headless;
+pub mod import;
+pub mod prefab;
+pub mod raycast;
 pub mod scene;
+pub mod serialization;
 pub mod shaders;
+pub mod shadows;
+pub mod time;
 
 pub use cgmath;
 pub use cgmath::*;
@@ -17,7 +27,11 @@ pub mod ecs {
     pub use wecs_derive as derive;
 
     pub mod reexports {
-        pub use super::{derive::Component, Component, Entity};
+        pub use super::{
+            derive::{Component, Reflect},
+            reflect::{self, Field, Reflect, ReflectError},
+            Component, Entity, Value,
+        };
         pub use std::{
             any::Any,
             sync::{Arc, RwLock},
@@ -25,6 +39,10 @@ pub mod ecs {
     }
 }
 
+pub use capture::{Capture, Frame};
 pub use engine::Engine;
+pub use headless::HeadlessEngine;
+pub use prefab::Prefab;
 pub use scene::Scene;
+pub use time::Time;
 pub use vulkano::image::SampleCount;
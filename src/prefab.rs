@@ -0,0 +1,200 @@
+use crate::{
+    assets::Library,
+    components::PrefabInstance,
+    ecs::{self, Entity, Registry, Value},
+    error::Error,
+    serialization::{EntityDescription, SceneDescription},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{Read, Write},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, RwLock, Weak,
+    },
+};
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Override {
+    pub entity: String,
+    pub component: String,
+    pub field: String,
+    pub value: Value,
+}
+
+impl Override {
+    pub fn new(entity: &str, component: &str, field: &str, value: Value) -> Self {
+        Self {
+            entity: entity.to_string(),
+            component: component.to_string(),
+            field: field.to_string(),
+            value,
+        }
+    }
+}
+
+pub struct Prefab {
+    pub id: Arc<String>,
+    template: RwLock<EntityDescription>,
+    instances: RwLock<Vec<Weak<PrefabInstance>>>,
+    count: AtomicUsize,
+}
+
+impl Prefab {
+    pub fn new(id: Arc<String>, template: EntityDescription) -> Arc<Self> {
+        Arc::new(Self {
+            id,
+            template: RwLock::new(template),
+            instances: RwLock::new(Vec::new()),
+            count: AtomicUsize::new(0),
+        })
+    }
+
+    pub fn from_entity(
+        id: Arc<String>,
+        entity: &Arc<Entity>,
+        library: &Library,
+        registry: &Registry,
+    ) -> Result<Arc<Self>, Error> {
+        Ok(Self::new(
+            id,
+            EntityDescription::from_entity(entity, Some(library), registry)?,
+        ))
+    }
+
+    pub fn load<R>(id: Arc<String>, reader: R) -> Result<Arc<Self>, Error>
+    where
+        R: Read,
+    {
+        Ok(Self::new(id, serde_json::from_reader(reader)?))
+    }
+
+    pub fn load_scene<R>(id: Arc<String>, reader: R) -> Result<Arc<Self>, Error>
+    where
+        R: Read,
+    {
+        let scene: SceneDescription = serde_json::from_reader(reader)?;
+
+        Ok(Self::new(id, scene.root))
+    }
+
+    pub fn save<W>(&self, writer: W) -> Result<(), Error>
+    where
+        W: Write,
+    {
+        serde_json::to_writer_pretty(writer, &*self.template.read().unwrap())?;
+
+        Ok(())
+    }
+
+    pub fn template(&self) -> EntityDescription {
+        self.template.read().unwrap().clone()
+    }
+
+    pub fn set_template(&self, template: EntityDescription) {
+        *self.template.write().unwrap() = template;
+    }
+
+    pub fn instances(&self) -> Vec<Arc<PrefabInstance>> {
+        let mut instances = self.instances.write().unwrap();
+
+        instances.retain(|instance| instance.strong_count() > 0);
+        instances.iter().filter_map(Weak::upgrade).collect()
+    }
+
+    pub fn instantiate(
+        self: &Arc<Self>,
+        overrides: Vec<Override>,
+        library: &Library,
+        registry: &Registry,
+    ) -> Result<Arc<Entity>, Error> {
+        let prefix = format!("{}#{}", self.id, self.count.fetch_add(1, Ordering::Relaxed));
+        let entity = self.build(&prefix, &overrides, library, registry)?;
+        let instance = PrefabInstance::new(ecs::id(&prefix), self.clone(), prefix, overrides);
+
+        entity.add(&instance);
+        self.instances
+            .write()
+            .unwrap()
+            .push(Arc::downgrade(&instance));
+
+        Ok(entity)
+    }
+
+    // Tracks an instance that was loaded rather than instantiated, and keeps fresh prefixes
+    // from colliding with its own.
+    pub(crate) fn adopt(&self, instance: &Arc<PrefabInstance>) {
+        let prefix = { instance.data.read().unwrap().prefix.clone() };
+
+        if let Some(n) = prefix
+            .rsplit_once('#')
+            .and_then(|(_, n)| n.parse::<usize>().ok())
+        {
+            self.count.fetch_max(n + 1, Ordering::Relaxed);
+        }
+
+        self.instances
+            .write()
+            .unwrap()
+            .push(Arc::downgrade(instance));
+    }
+
+    pub fn apply(&self, library: &Library, registry: &Registry) -> Result<(), Error> {
+        for instance in self.instances() {
+            instance.apply(library, registry)?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn build(
+        &self,
+        prefix: &str,
+        overrides: &[Override],
+        library: &Library,
+        registry: &Registry,
+    ) -> Result<Arc<Entity>, Error> {
+        let mut description = self.template();
+
+        rename(&mut description, prefix);
+
+        let entity = description.to_entity(Some(library), registry)?;
+        let descendants = entity.descendants();
+
+        for o in overrides {
+            let entity_id = format!("{}:{}", prefix, o.entity);
+            let component_id = format!("{}:{}", prefix, o.component);
+            let component = descendants
+                .iter()
+                .filter(|entity| *entity.id == entity_id)
+                .find_map(|entity| {
+                    entity
+                        .components()
+                        .read()
+                        .unwrap()
+                        .iter()
+                        .find(|component| *component.id() == component_id)
+                        .cloned()
+                })
+                .ok_or_else(|| Error::InvalidOverride(format!("{}/{}", o.entity, o.component)))?;
+
+            component.set_field(&o.field, o.value.clone())?;
+        }
+
+        Ok(entity)
+    }
+}
+
+fn rename(description: &mut EntityDescription, prefix: &str) {
+    description.id = format!("{}:{}", prefix, description.id);
+
+    for component in &mut description.components {
+        let id = format!("{}:{}", prefix, component.id());
+
+        component.set_id(id);
+    }
+
+    for child in &mut description.children {
+        rename(child, prefix);
+    }
+}
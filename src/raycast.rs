@@ -0,0 +1,188 @@
+use crate::{
+    assets::MeshData,
+    bounds::Ray,
+    components::{Model, Transform},
+    ecs::Entity,
+    scene::Scene,
+};
+use cgmath::{InnerSpace, Matrix, Matrix4, SquareMatrix, Vector3};
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub struct RaycastHit {
+    pub entity: Arc<Entity>,
+    pub model: Arc<Model>,
+    pub distance: f32,
+    pub point: Vector3<f32>,
+    pub barycentric: Vector3<f32>,
+    pub normal: Vector3<f32>,
+    pub triangle: usize,
+}
+
+impl Model {
+    pub fn raycast(&self, ray: &Ray) -> Option<(f32, Vector3<f32>, Vector3<f32>, usize)> {
+        let (aabb, sphere) = self.world_bounds()?;
+
+        ray.intersect_sphere(&sphere)?;
+        ray.intersect_aabb(&aabb)?;
+
+        let entity = { self.entity.read().unwrap().clone() }?;
+        let world = entity.first_of::<Transform>()?.world_matrix();
+        let data = self.data.read().unwrap();
+
+        data.mesh.data.raycast(&world, ray)
+    }
+}
+
+impl MeshData {
+    // Distances and normals are in world space, as if the mesh were placed by `world`.
+    pub fn raycast(
+        &self,
+        world: &Matrix4<f32>,
+        ray: &Ray,
+    ) -> Option<(f32, Vector3<f32>, Vector3<f32>, usize)> {
+        let inverse = world.invert()?;
+        let local = ray.transform(&inverse);
+        let mut closest: Option<(f32, Vector3<f32>, Vector3<f32>, usize)> = None;
+
+        for (i, triangle) in self.triangles().enumerate() {
+            let [a, b, c] = triangle.map(|index| self.positions[index as usize]);
+
+            if let Some((t, barycentric)) = local.intersect_triangle(a, b, c) {
+                let point = (world * local.at(t).extend(1.0)).truncate();
+                let distance = (point - ray.origin).magnitude();
+
+                if closest.map(|(d, ..)| distance < d).unwrap_or(true) {
+                    let normal = (inverse.transpose() * (b - a).cross(c - a).extend(0.0))
+                        .truncate()
+                        .normalize();
+
+                    closest = Some((distance, barycentric, normal, i));
+                }
+            }
+        }
+
+        closest
+    }
+}
+
+impl Scene {
+    pub fn raycast_all(&self, ray: &Ray) -> Vec<RaycastHit> {
+        let mut hits = self
+            .root
+            .query::<&Model>()
+            .into_iter()
+            .filter(|(_, model)| model.data.read().unwrap().visible)
+            .filter_map(|(entity, model)| {
+                let (distance, barycentric, normal, triangle) = model.raycast(ray)?;
+
+                Some(RaycastHit {
+                    entity,
+                    model,
+                    distance,
+                    point: ray.at(distance),
+                    barycentric,
+                    normal,
+                    triangle,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        hits.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+
+        hits
+    }
+
+    pub fn raycast(&self, ray: &Ray) -> Option<RaycastHit> {
+        self.raycast_all(ray).into_iter().next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{Deg, Rotation3};
+
+    // Two unit quads facing +z, one at z = 0 and one behind it at z = -1.
+    fn layers() -> MeshData {
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+
+        for z in [-1.0, 0.0] {
+            let base = positions.len() as u32;
+
+            positions.extend([
+                Vector3::new(-1.0, -1.0, z),
+                Vector3::new(1.0, -1.0, z),
+                Vector3::new(1.0, 1.0, z),
+                Vector3::new(-1.0, 1.0, z),
+            ]);
+            indices.extend([0, 1, 2, 0, 2, 3].map(|i| base + i));
+        }
+
+        MeshData::new(positions, indices)
+    }
+
+    fn assert_near(a: Vector3<f32>, b: Vector3<f32>) {
+        assert!((a - b).magnitude() < 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn the_closest_triangle_wins() {
+        let data = layers();
+        let ray = Ray::new(Vector3::new(0.5, -0.5, 5.0), -Vector3::unit_z());
+        let (distance, barycentric, normal, triangle) =
+            data.raycast(&Matrix4::identity(), &ray).unwrap();
+
+        assert!((distance - 5.0).abs() < 1e-5);
+        assert_eq!(triangle, 2);
+        assert_near(barycentric, Vector3::new(0.25, 0.5, 0.25));
+        assert_near(normal, Vector3::unit_z());
+
+        // From behind, the other layer is closer.
+        let ray = Ray::new(Vector3::new(0.5, -0.5, -5.0), Vector3::unit_z());
+
+        assert_eq!(data.raycast(&Matrix4::identity(), &ray).unwrap().3, 0);
+    }
+
+    #[test]
+    fn rays_that_miss_return_nothing() {
+        let data = layers();
+        let world = Matrix4::identity();
+
+        assert!(data
+            .raycast(
+                &world,
+                &Ray::new(Vector3::new(2.0, 0.0, 5.0), -Vector3::unit_z())
+            )
+            .is_none());
+        assert!(data
+            .raycast(
+                &world,
+                &Ray::new(Vector3::new(0.0, 0.0, 5.0), Vector3::unit_z())
+            )
+            .is_none());
+        assert!(data
+            .raycast(
+                &world,
+                &Ray::new(Vector3::new(0.0, 0.0, 5.0), Vector3::unit_x())
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn hits_are_reported_in_world_space() {
+        let data = layers();
+        let world = Matrix4::from_translation(Vector3::new(10.0, 0.0, 2.0))
+            * Matrix4::from(cgmath::Quaternion::from_angle_y(Deg(90.0)))
+            * Matrix4::from_nonuniform_scale(1.0, 1.0, 3.0);
+
+        // The front layer now faces +x at x = 10 and the back one sits at x = 7.
+        let ray = Ray::new(Vector3::new(20.0, 0.5, 2.0), -Vector3::unit_x());
+        let (distance, _, normal, triangle) = data.raycast(&world, &ray).unwrap();
+
+        assert!((distance - 10.0).abs() < 1e-4);
+        assert!(triangle >= 2);
+        assert_near(normal, Vector3::unit_x());
+    }
+}
@@ -1,41 +1,134 @@
 use crate::{
-    components::{Camera, Light, LIGHT_ID},
-    ecs,
-    ecs::{Entity, ENTITY_ID},
+    bounds::CullStats,
+    components::{Camera, Light, TransformPropagation},
+    ecs::{ComponentUpdates, Entity, Schedule, ScheduleError, Stage},
+    time::Time,
 };
 use cgmath::Vector4;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, RwLock, RwLockReadGuard};
 
 pub struct Scene {
     pub root: Arc<Entity>,
     pub camera: RwLock<Arc<Camera>>,
     pub bg: RwLock<Vector4<f32>>,
+    pub time: RwLock<Time>,
+    schedule: RwLock<Schedule>,
+    pub stats: RwLock<CullStats>,
 }
 
 impl Scene {
     pub fn new(root: &Arc<Entity>, camera: Arc<Camera>, bg: Vector4<f32>) -> Arc<Self> {
+        let mut schedule = Schedule::new();
+
+        schedule
+            .add(Stage::Update, Arc::new(ComponentUpdates), &[], &[])
+            .unwrap();
+        schedule
+            .add(Stage::RenderPrep, Arc::new(TransformPropagation), &[], &[])
+            .unwrap();
+
         Arc::new(Self {
             root: root.clone(),
             camera: RwLock::new(camera),
             bg: RwLock::new(bg),
+            time: RwLock::new(Time::new()),
+            schedule: RwLock::new(schedule),
+            stats: RwLock::new(CullStats::default()),
         })
     }
 
-    fn get_lights_inner(entity: Arc<Entity>, lights: &mut Vec<Arc<Light>>) {
-        let mut l = entity.get_type(ecs::id(LIGHT_ID));
+    pub fn schedule(&self) -> RwLockReadGuard<'_, Schedule> {
+        self.schedule.read().unwrap()
+    }
 
-        lights.append(&mut l);
+    // Ordering problems are caught here rather than on every frame, so a scene's schedule can
+    // always run.
+    pub fn set_schedule(&self, schedule: Schedule) -> Result<(), ScheduleError> {
+        schedule.order()?;
+        *self.schedule.write().unwrap() = schedule;
 
-        for entity in &entity.get_type::<Entity>(ecs::id(ENTITY_ID)) {
-            Self::get_lights_inner(entity.clone(), lights);
-        }
+        Ok(())
     }
 
     pub fn get_lights(&self) -> Vec<Arc<Light>> {
-        let mut lights = Vec::new();
+        self.root
+            .query::<&Light>()
+            .into_iter()
+            .map(|(_, light)| light)
+            .collect()
+    }
+
+    pub fn cameras(&self) -> Vec<Arc<Camera>> {
+        let mut cameras = self
+            .root
+            .query::<&Camera>()
+            .into_iter()
+            .map(|(_, camera)| camera)
+            .filter(|camera| camera.data.read().unwrap().active)
+            .collect::<Vec<_>>();
+
+        if cameras.is_empty() {
+            cameras.push(self.camera.read().unwrap().clone());
+        }
+
+        cameras.sort_by_key(|camera| {
+            let data = camera.data.read().unwrap();
+
+            (data.target.is_none(), data.priority)
+        });
+
+        cameras
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::{self, System};
+
+    struct Named(&'static str);
+
+    impl System for Named {
+        fn id(&self) -> Arc<String> {
+            ecs::id(self.0)
+        }
+
+        fn run(&self, _: &Arc<Entity>) {}
+    }
+
+    #[test]
+    fn invalid_schedules_are_rejected_when_set() {
+        let root = Entity::new(ecs::id("root"));
+        let scene = Scene::new(
+            &root,
+            Camera::new(ecs::id("camera"), 1.0, 0.1, 100.0),
+            Vector4::new(0.0, 0.0, 0.0, 1.0),
+        );
+        let mut schedule = Schedule::new();
+
+        schedule
+            .add(Stage::Update, Arc::new(Named("a")), &["b"], &[])
+            .unwrap();
+        schedule
+            .add(Stage::Update, Arc::new(Named("b")), &["a"], &[])
+            .unwrap();
+
+        assert!(matches!(
+            scene.set_schedule(schedule),
+            Err(ScheduleError::Cycle(Stage::Update))
+        ));
+        assert_eq!(scene.schedule().systems().len(), 2);
+        assert!(scene.schedule().run(&root).is_ok());
+
+        let mut schedule = Schedule::new();
 
-        Self::get_lights_inner(self.root.clone(), &mut lights);
+        schedule
+            .add(Stage::Update, Arc::new(Named("a")), &["missing"], &[])
+            .unwrap();
 
-        lights
+        assert!(matches!(
+            scene.set_schedule(schedule),
+            Err(ScheduleError::UnknownSystem(_))
+        ));
     }
 }
@@ -0,0 +1,716 @@
+use crate::{
+    assets::{Library, Material, PbrMaterial, Texture},
+    components::{
+        Camera, Clear, Light, LightType, Model, PrefabInstance, Projection, Rect, Transform,
+    },
+    ecs::{self, reflect, Component, Entity, Registry, Value},
+    error::Error,
+    prefab::Override,
+    scene::Scene,
+};
+use cgmath::{Quaternion, Vector3, Vector4};
+use serde::{Deserialize, Serialize};
+use serde_json::Map;
+use std::{
+    io::{Read, Write},
+    sync::Arc,
+};
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MaterialDescription {
+    pub ambient: f32,
+    pub diff_strength: f32,
+    pub spec_strength: f32,
+    pub spec_power: u32,
+    #[serde(default = "default_one")]
+    pub normal_scale: f32,
+    #[serde(default)]
+    pub normal_texture: Option<String>,
+    #[serde(default)]
+    pub pbr: Option<PbrDescription>,
+}
+
+impl MaterialDescription {
+    pub fn from_material(material: &Material, library: &Library) -> Result<Self, Error> {
+        let pbr = match &material.pbr {
+            Some(pbr) => Some(PbrDescription::from_pbr(pbr, library)?),
+            None => None,
+        };
+
+        Ok(Self {
+            ambient: material.ambient,
+            diff_strength: material.diff_strength,
+            spec_strength: material.spec_strength,
+            spec_power: material.spec_power,
+            normal_scale: material.normal_scale,
+            normal_texture: texture_path(&material.normal_texture, library)?,
+            pbr,
+        })
+    }
+
+    pub fn to_material(&self, library: &Library) -> Result<Arc<Material>, Error> {
+        let pbr = match &self.pbr {
+            Some(pbr) => Some(pbr.to_pbr(library)?),
+            None => None,
+        };
+
+        Ok(Arc::new(Material {
+            ambient: self.ambient,
+            diff_strength: self.diff_strength,
+            spec_strength: self.spec_strength,
+            spec_power: self.spec_power,
+            normal_scale: self.normal_scale,
+            normal_texture: data_texture(&self.normal_texture, library)?,
+            pbr,
+        }))
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PbrDescription {
+    pub base_color: [f32; 4],
+    pub base_color_texture: Option<String>,
+    pub metallic: f32,
+    pub metallic_texture: Option<String>,
+    pub roughness: f32,
+    pub roughness_texture: Option<String>,
+    pub occlusion_strength: f32,
+    pub occlusion_texture: Option<String>,
+    pub emissive: [f32; 3],
+    pub emissive_texture: Option<String>,
+}
+
+fn texture_path(
+    texture: &Option<Arc<Texture>>,
+    library: &Library,
+) -> Result<Option<String>, Error> {
+    match texture {
+        Some(texture) => library
+            .texture_path(texture)
+            .map(Some)
+            .ok_or_else(|| Error::MissingAsset("material texture".to_string())),
+        None => Ok(None),
+    }
+}
+
+// Only assets need a library, so scenes made of plain components can go without one.
+fn require<'a>(library: Option<&'a Library>, asset: &str) -> Result<&'a Library, Error> {
+    library.ok_or_else(|| Error::MissingAsset(asset.to_string()))
+}
+
+fn texture(path: &Option<String>, library: &Library) -> Result<Option<Arc<Texture>>, Error> {
+    match path {
+        Some(path) => Ok(Some(library.texture(path)?)),
+        None => Ok(None),
+    }
+}
+
+fn data_texture(path: &Option<String>, library: &Library) -> Result<Option<Arc<Texture>>, Error> {
+    match path {
+        Some(path) => Ok(Some(library.data_texture(path)?)),
+        None => Ok(None),
+    }
+}
+
+impl PbrDescription {
+    pub fn from_pbr(pbr: &PbrMaterial, library: &Library) -> Result<Self, Error> {
+        Ok(Self {
+            base_color: pbr.base_color.into(),
+            base_color_texture: texture_path(&pbr.base_color_texture, library)?,
+            metallic: pbr.metallic,
+            metallic_texture: texture_path(&pbr.metallic_texture, library)?,
+            roughness: pbr.roughness,
+            roughness_texture: texture_path(&pbr.roughness_texture, library)?,
+            occlusion_strength: pbr.occlusion_strength,
+            occlusion_texture: texture_path(&pbr.occlusion_texture, library)?,
+            emissive: pbr.emissive.into(),
+            emissive_texture: texture_path(&pbr.emissive_texture, library)?,
+        })
+    }
+
+    pub fn to_pbr(&self, library: &Library) -> Result<PbrMaterial, Error> {
+        Ok(PbrMaterial {
+            base_color: Vector4::from(self.base_color),
+            base_color_texture: texture(&self.base_color_texture, library)?,
+            metallic: self.metallic,
+            metallic_texture: data_texture(&self.metallic_texture, library)?,
+            roughness: self.roughness,
+            roughness_texture: data_texture(&self.roughness_texture, library)?,
+            occlusion_strength: self.occlusion_strength,
+            occlusion_texture: data_texture(&self.occlusion_texture, library)?,
+            emissive: Vector3::from(self.emissive),
+            emissive_texture: texture(&self.emissive_texture, library)?,
+        })
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ShadowDescription {
+    pub enabled: bool,
+    pub bias: f32,
+    pub normal_bias: f32,
+    pub strength: f32,
+    pub range: f32,
+}
+
+impl Default for ShadowDescription {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bias: 0.005,
+            normal_bias: 0.02,
+            strength: 1.0,
+            range: 50.0,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_one() -> f32 {
+    1.0
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TargetDescription {
+    pub path: String,
+    pub dimensions: [u32; 2],
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ComponentDescription {
+    Transform {
+        id: String,
+        position: [f32; 3],
+        rotation: [f32; 4],
+        scale: [f32; 3],
+    },
+    Camera {
+        id: String,
+        projection: Projection,
+        viewport: Rect,
+        priority: i32,
+        clear: Clear,
+        active: bool,
+        #[serde(default)]
+        target: Option<TargetDescription>,
+    },
+    Light {
+        id: String,
+        light_type: LightType,
+        color: [f32; 3],
+        intensity: f32,
+        #[serde(default)]
+        shadow: ShadowDescription,
+    },
+    Model {
+        id: String,
+        mesh: String,
+        texture: String,
+        material: MaterialDescription,
+        color: [f32; 4],
+        visible: bool,
+        lit: bool,
+        #[serde(default = "default_true")]
+        casts_shadows: bool,
+        #[serde(default = "default_true")]
+        receives_shadows: bool,
+    },
+    PrefabInstance {
+        id: String,
+        prefab: String,
+        prefix: String,
+        overrides: Vec<Override>,
+    },
+    Reflected {
+        tid: String,
+        id: String,
+        fields: Map<String, Value>,
+    },
+}
+
+impl ComponentDescription {
+    pub fn id(&self) -> &str {
+        match self {
+            Self::Transform { id, .. }
+            | Self::Camera { id, .. }
+            | Self::Light { id, .. }
+            | Self::Model { id, .. }
+            | Self::PrefabInstance { id, .. }
+            | Self::Reflected { id, .. } => id,
+        }
+    }
+
+    pub fn set_id(&mut self, value: String) {
+        match self {
+            Self::Transform { id, .. }
+            | Self::Camera { id, .. }
+            | Self::Light { id, .. }
+            | Self::Model { id, .. }
+            | Self::PrefabInstance { id, .. }
+            | Self::Reflected { id, .. } => *id = value,
+        }
+    }
+
+    pub fn from_component(
+        component: &Arc<dyn Component>,
+        library: Option<&Library>,
+        registry: &Registry,
+    ) -> Result<Option<Self>, Error> {
+        let any = component.clone().as_any();
+
+        if let Some(transform) = any.downcast_ref::<Transform>() {
+            let data = transform.data();
+
+            return Ok(Some(Self::Transform {
+                id: transform.id.to_string(),
+                position: data.position.into(),
+                rotation: data.rotation.into(),
+                scale: data.scale.into(),
+            }));
+        }
+
+        if let Some(camera) = any.downcast_ref::<Camera>() {
+            let data = camera.data.read().unwrap();
+            let target = match &data.target {
+                Some(target) => Some(TargetDescription {
+                    path: require(library, "camera target")?
+                        .texture_path(target)
+                        .ok_or_else(|| Error::MissingAsset("camera target".to_string()))?,
+                    dimensions: target.dimensions(),
+                }),
+                None => None,
+            };
+
+            return Ok(Some(Self::Camera {
+                id: camera.id.to_string(),
+                projection: data.projection,
+                viewport: data.viewport,
+                priority: data.priority,
+                clear: data.clear,
+                active: data.active,
+                target,
+            }));
+        }
+
+        if let Some(light) = any.downcast_ref::<Light>() {
+            let data = light.data.read().unwrap();
+
+            return Ok(Some(Self::Light {
+                id: light.id.to_string(),
+                light_type: data.light_type,
+                color: data.color.into(),
+                intensity: data.intensity,
+                shadow: ShadowDescription {
+                    enabled: data.shadows && data.light_type.supports_shadows(),
+                    bias: data.bias,
+                    normal_bias: data.normal_bias,
+                    strength: data.shadow_strength,
+                    range: data.shadow_range,
+                },
+            }));
+        }
+
+        if let Some(model) = any.downcast_ref::<Model>() {
+            let data = model.data.read().unwrap();
+            let library = require(library, &model.id)?;
+            let mesh = library
+                .mesh_path(&data.mesh)
+                .ok_or_else(|| Error::MissingAsset(model.id.to_string()))?;
+            let texture = library
+                .texture_path(&data.texture)
+                .ok_or_else(|| Error::MissingAsset(model.id.to_string()))?;
+
+            return Ok(Some(Self::Model {
+                id: model.id.to_string(),
+                mesh,
+                texture,
+                material: MaterialDescription::from_material(&data.material, library)?,
+                color: data.color.into(),
+                visible: data.visible,
+                lit: data.lit,
+                casts_shadows: data.casts_shadows,
+                receives_shadows: data.receives_shadows,
+            }));
+        }
+
+        if let Some(instance) = any.downcast_ref::<PrefabInstance>() {
+            let data = instance.data.read().unwrap();
+            let prefab = require(library, &instance.id)?
+                .prefab_path(&data.prefab)
+                .ok_or_else(|| Error::MissingAsset(data.prefab.id.to_string()))?;
+
+            return Ok(Some(Self::PrefabInstance {
+                id: instance.id.to_string(),
+                prefab,
+                prefix: data.prefix.clone(),
+                overrides: data.overrides.clone(),
+            }));
+        }
+
+        let tid = component.tid();
+
+        if registry.contains(&tid) {
+            return Ok(Some(Self::Reflected {
+                tid: tid.to_string(),
+                id: component.id().to_string(),
+                fields: reflect::values(component.as_ref()),
+            }));
+        }
+
+        Ok(None)
+    }
+
+    pub fn to_component(
+        &self,
+        library: Option<&Library>,
+        registry: &Registry,
+    ) -> Result<Arc<dyn Component>, Error> {
+        let component: Arc<dyn Component> = match self {
+            Self::Transform {
+                id,
+                position,
+                rotation,
+                scale,
+            } => Transform::new(
+                ecs::id(id),
+                Vector3::from(*position),
+                Quaternion::from(*rotation),
+                Vector3::from(*scale),
+            ),
+
+            Self::Camera {
+                id,
+                projection,
+                viewport,
+                priority,
+                clear,
+                active,
+                target,
+            } => {
+                let camera = Camera::with_projection(ecs::id(id), *projection);
+                let target = match target {
+                    Some(target) => Some(
+                        require(library, &target.path)?
+                            .render_target(&target.path, target.dimensions)?,
+                    ),
+                    None => None,
+                };
+
+                {
+                    let mut data = camera.data.write().unwrap();
+
+                    data.viewport = *viewport;
+                    data.priority = *priority;
+                    data.clear = *clear;
+                    data.active = *active;
+                    data.target = target;
+                }
+
+                camera
+            }
+
+            Self::Light {
+                id,
+                light_type,
+                color,
+                intensity,
+                shadow,
+            } => {
+                let light = Light::new(ecs::id(id), *light_type, Vector3::from(*color), *intensity);
+
+                light.set_shadows(shadow.enabled)?;
+
+                {
+                    let mut data = light.data.write().unwrap();
+
+                    data.bias = shadow.bias;
+                    data.normal_bias = shadow.normal_bias;
+                    data.shadow_strength = shadow.strength;
+                    data.shadow_range = shadow.range;
+                }
+
+                light
+            }
+
+            Self::Model {
+                id,
+                mesh,
+                texture,
+                material,
+                color,
+                visible,
+                lit,
+                casts_shadows,
+                receives_shadows,
+            } => {
+                let library = require(library, id)?;
+                let model = Model::new(
+                    ecs::id(id),
+                    library.mesh(mesh)?,
+                    library.texture(texture)?,
+                    material.to_material(library)?,
+                    Vector4::from(*color),
+                    *visible,
+                    *lit,
+                );
+
+                {
+                    let mut data = model.data.write().unwrap();
+
+                    data.casts_shadows = *casts_shadows;
+                    data.receives_shadows = *receives_shadows;
+                }
+
+                model
+            }
+
+            Self::PrefabInstance {
+                id,
+                prefab,
+                prefix,
+                overrides,
+            } => {
+                let prefab = require(library, prefab)?.prefab(prefab)?;
+                let instance = PrefabInstance::new(
+                    ecs::id(id),
+                    prefab.clone(),
+                    prefix.clone(),
+                    overrides.clone(),
+                );
+
+                prefab.adopt(&instance);
+
+                instance
+            }
+
+            Self::Reflected { tid, id, fields } => registry.construct(tid, ecs::id(id), fields)?,
+        };
+
+        Ok(component)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EntityDescription {
+    pub id: String,
+    pub components: Vec<ComponentDescription>,
+    pub children: Vec<EntityDescription>,
+}
+
+impl EntityDescription {
+    pub fn from_entity(
+        entity: &Arc<Entity>,
+        library: Option<&Library>,
+        registry: &Registry,
+    ) -> Result<Self, Error> {
+        let mut components = Vec::new();
+        let mut children = Vec::new();
+
+        for component in &*entity.components().read().unwrap() {
+            match component.clone().as_any().downcast::<Entity>() {
+                Ok(child) => children.push(Self::from_entity(&child, library, registry)?),
+
+                Err(_) => {
+                    if let Some(description) =
+                        ComponentDescription::from_component(component, library, registry)?
+                    {
+                        components.push(description);
+                    }
+                }
+            }
+        }
+
+        Ok(Self {
+            id: entity.id.to_string(),
+            components,
+            children,
+        })
+    }
+
+    pub fn to_entity(
+        &self,
+        library: Option<&Library>,
+        registry: &Registry,
+    ) -> Result<Arc<Entity>, Error> {
+        let entity = Entity::new(ecs::id(&self.id));
+
+        for component in &self.components {
+            entity.add_dyn(component.to_component(library, registry)?);
+        }
+
+        for child in &self.children {
+            entity.add(&child.to_entity(library, registry)?);
+        }
+
+        Ok(entity)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SceneDescription {
+    pub root: EntityDescription,
+    pub camera: String,
+    pub bg: [f32; 4],
+}
+
+impl SceneDescription {
+    pub fn from_scene(
+        scene: &Scene,
+        library: Option<&Library>,
+        registry: &Registry,
+    ) -> Result<Self, Error> {
+        let camera = scene.camera.read().unwrap().id.to_string();
+
+        Ok(Self {
+            root: EntityDescription::from_entity(&scene.root, library, registry)?,
+            camera,
+            bg: (*scene.bg.read().unwrap()).into(),
+        })
+    }
+
+    pub fn to_scene(
+        &self,
+        library: Option<&Library>,
+        registry: &Registry,
+    ) -> Result<Arc<Scene>, Error> {
+        let root = self.root.to_entity(library, registry)?;
+        let camera = root
+            .query::<&Camera>()
+            .into_iter()
+            .map(|(_, camera)| camera)
+            .find(|camera| *camera.id == self.camera)
+            .ok_or_else(|| Error::MissingCamera(self.camera.clone()))?;
+
+        Ok(Scene::new(&root, camera, Vector4::from(self.bg)))
+    }
+}
+
+impl Scene {
+    pub fn save<W>(
+        &self,
+        writer: W,
+        library: Option<&Library>,
+        registry: &Registry,
+    ) -> Result<(), Error>
+    where
+        W: Write,
+    {
+        serde_json::to_writer_pretty(
+            writer,
+            &SceneDescription::from_scene(self, library, registry)?,
+        )?;
+
+        Ok(())
+    }
+
+    pub fn load<R>(
+        reader: R,
+        library: Option<&Library>,
+        registry: &Registry,
+    ) -> Result<Arc<Self>, Error>
+    where
+        R: Read,
+    {
+        serde_json::from_reader::<_, SceneDescription>(reader)?.to_scene(library, registry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{Deg, Rotation3};
+
+    fn scene() -> Arc<Scene> {
+        let root = Entity::new(ecs::id("root"));
+        let eye = Entity::new(ecs::id("eye"));
+        let sun = Entity::new(ecs::id("sun"));
+        let camera = Camera::with_projection(
+            ecs::id("camera"),
+            Projection::ReverseZ {
+                fov: 1.2,
+                near: 0.05,
+            },
+        );
+        let light = Light::directional(ecs::id("light"), Vector3::new(1.0, 0.9, 0.8), 2.5);
+
+        {
+            let mut data = camera.data.write().unwrap();
+
+            data.viewport = Rect::new(0.0, 0.0, 0.5, 1.0);
+            data.priority = 3;
+            data.clear = Clear::Color(Vector4::new(0.1, 0.2, 0.3, 1.0));
+        }
+
+        light.set_shadows(true).unwrap();
+        light.data.write().unwrap().shadow_range = 20.0;
+
+        eye.add(&Transform::new(
+            ecs::id("eye transform"),
+            Vector3::new(0.0, 1.0, 5.0),
+            Quaternion::from_angle_y(Deg(30.0)),
+            Vector3::new(1.0, 2.0, 1.0),
+        ));
+        eye.add(&camera);
+        sun.add(&light);
+        root.add(&eye);
+        root.add(&sun);
+
+        Scene::new(&root, camera, Vector4::new(0.5, 0.5, 0.5, 1.0))
+    }
+
+    fn save(scene: &Scene, registry: &Registry) -> String {
+        let mut json = Vec::new();
+
+        scene.save(&mut json, None, registry).unwrap();
+
+        String::from_utf8(json).unwrap()
+    }
+
+    #[test]
+    fn plain_components_round_trip_without_a_library() {
+        let registry = Registry::new();
+        let json = save(&scene(), &registry);
+        let scene = Scene::load(json.as_bytes(), None, &registry).unwrap();
+
+        assert_eq!(save(&scene, &registry), json);
+
+        let camera = scene.camera.read().unwrap().clone();
+        let data = camera.data.read().unwrap();
+
+        assert_eq!(*camera.id, "camera");
+        assert!(data.projection.reverse_z());
+        assert_eq!(data.priority, 3);
+        assert_eq!(data.clear, Clear::Color(Vector4::new(0.1, 0.2, 0.3, 1.0)));
+
+        let (_, light) = scene.root.query::<&Light>().into_iter().next().unwrap();
+        let data = light.data.read().unwrap();
+
+        assert!(data.shadows);
+        assert_eq!(data.shadow_range, 20.0);
+        assert_eq!(data.intensity, 2.5);
+
+        let (_, transform) = scene.root.query::<&Transform>().into_iter().next().unwrap();
+
+        assert_eq!(transform.data().position, Vector3::new(0.0, 1.0, 5.0));
+        assert_eq!(transform.data().scale, Vector3::new(1.0, 2.0, 1.0));
+    }
+
+    #[test]
+    fn assets_without_a_library_are_missing() {
+        let description = ComponentDescription::PrefabInstance {
+            id: "instance".to_string(),
+            prefab: "tree.json".to_string(),
+            prefix: "tree".to_string(),
+            overrides: Vec::new(),
+        };
+
+        assert!(matches!(
+            description.to_component(None, &Registry::new()),
+            Err(Error::MissingAsset(asset)) if asset == "tree.json"
+        ));
+    }
+}
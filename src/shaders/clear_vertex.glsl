@@ -0,0 +1,15 @@
+#version 450
+
+layout(location = 0) out vec4 v_color;
+
+layout(push_constant) uniform Clear {
+    vec4 color;
+    float depth;
+} clear;
+
+void main() {
+    vec2 position = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+
+    v_color = clear.color;
+    gl_Position = vec4(position * 2.0 - 1.0, clear.depth, 1.0);
+}
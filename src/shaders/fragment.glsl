@@ -1,16 +1,30 @@
 #version 450
 #define MAX_LIGHTS 256
+#define SHADOW_ATLAS_TILES 4
+#define DIRECTIONAL_LIGHT 0u
+#define POINT_LIGHT 1u
+#define SPOT_LIGHT 2u
+#define METALLIC_TEXTURE 1u
+#define ROUGHNESS_TEXTURE 2u
+#define NORMAL_TEXTURE 4u
+#define OCCLUSION_TEXTURE 8u
+#define EMISSIVE_TEXTURE 16u
+#define PI 3.14159265359
 
 struct Light {
-    mat4 position;
-    mat4 rotation;
     mat4 proj;
+    vec3 position;
+    uint kind;
+    vec3 direction;
+    float range;
     vec3 color;
-    bool directional;
     float intensity;
-    float cutoff;
+    float inner_cutoff;
     float outer_cutoff;
-    float attenuation;
+    int shadow;
+    float bias;
+    float normal_bias;
+    float shadow_strength;
 };
 
 struct LightArray {
@@ -21,11 +35,17 @@ struct LightArray {
 layout(location = 0) in vec3 normal;
 layout(location = 1) in vec2 tex_coord;
 layout(location = 2) in vec4 f_pos;
-layout(location = 3) in mat4 global_rotation;
+layout(location = 3) in vec4 tangent;
 
 layout(location = 0) out vec4 f_color;
 
 layout(set = 1, binding = 0) uniform sampler2D tex;
+layout(set = 1, binding = 1) uniform sampler2D shadow_atlas;
+layout(set = 1, binding = 2) uniform sampler2D metallic_tex;
+layout(set = 1, binding = 3) uniform sampler2D roughness_tex;
+layout(set = 1, binding = 4) uniform sampler2D normal_tex;
+layout(set = 1, binding = 5) uniform sampler2D occlusion_tex;
+layout(set = 1, binding = 6) uniform sampler2D emissive_tex;
 
 layout(set = 0, binding = 1) uniform Data {
     bool lit;
@@ -34,52 +54,220 @@ layout(set = 0, binding = 1) uniform Data {
     float diff_strength;
     float spec_strength;
     uint spec_power;
+    bool receives_shadows;
+    vec3 camera_position;
+    bool pbr;
+    vec4 base_color;
+    vec3 emissive;
+    float metallic;
+    float roughness;
+    float normal_scale;
+    float occlusion_strength;
+    uint textures;
     LightArray lights;
 } uniforms;
 
-vec4 light_calculations(vec3 norm) {
-    vec4 brightness = vec4(uniforms.ambient);
+float shadow_calculations(Light light, vec3 norm) {
+    if (light.shadow < 0 || !uniforms.receives_shadows) {
+        return 1.0;
+    }
+
+    vec4 light_space = light.proj * vec4(f_pos.xyz + norm * light.normal_bias, 1.0);
+    vec3 coords = light_space.xyz / light_space.w;
+    vec2 uv = coords.xy * 0.5 + 0.5;
+
+    if (coords.z > 1.0 || any(lessThan(uv, vec2(0.0))) || any(greaterThan(uv, vec2(1.0)))) {
+        return 1.0;
+    }
+
+    float tile_size = 1.0 / float(SHADOW_ATLAS_TILES);
+    vec2 tile = vec2(light.shadow % SHADOW_ATLAS_TILES, light.shadow / SHADOW_ATLAS_TILES) * tile_size;
+    vec2 texel = 1.0 / vec2(textureSize(shadow_atlas, 0)) / tile_size;
+    float shadow = 0.0;
+
+    for (int x = -1; x <= 1; x++) {
+        for (int y = -1; y <= 1; y++) {
+            vec2 sample_uv = clamp(uv + vec2(x, y) * texel, texel * 0.5, 1.0 - texel * 0.5);
+            float depth = texture(shadow_atlas, tile + sample_uv * tile_size).r;
+
+            shadow += coords.z - light.bias > depth ? 1.0 : 0.0;
+        }
+    }
+
+    return 1.0 - shadow / 9.0 * light.shadow_strength;
+}
+
+float light_attenuation(Light light, out vec3 light_dir) {
+    if (light.kind == DIRECTIONAL_LIGHT) {
+        light_dir = -normalize(light.direction);
+
+        return 1.0;
+    }
+
+    vec3 f_pos_dif = light.position - f_pos.xyz;
+    float dist = length(f_pos_dif);
+    float falloff = clamp(1.0 - pow(dist / light.range, 4.0), 0.0, 1.0);
+    float attenuation = falloff * falloff / (dist * dist + 1.0);
+
+    light_dir = normalize(f_pos_dif);
+
+    if (light.kind == SPOT_LIGHT) {
+        float theta = dot(light_dir, -normalize(light.direction));
+        float epsilon = max(light.inner_cutoff - light.outer_cutoff, 0.0001);
+
+        attenuation *= clamp((theta - light.outer_cutoff) / epsilon, 0.0, 1.0);
+    }
+
+    return attenuation;
+}
+
+void light_calculations(vec3 norm, out vec3 diffuse, out vec3 specular) {
+    vec3 view_dir = normalize(uniforms.camera_position - f_pos.xyz);
+
+    diffuse = vec3(uniforms.ambient);
+    specular = vec3(0.0);
 
     for (uint i = 0; i < uniforms.lights.len; i++) {
         Light light = uniforms.lights.array[i];
 
-        vec3 f_pos_dif = vec3((-(light.position * vec4(vec3(0.0), 1.0)) - f_pos).xyz);
-        vec3 light_dir = normalize(f_pos_dif);
+        vec3 light_dir;
+        float attenuation = light_attenuation(light, light_dir);
+        float n_dot_l = dot(norm, light_dir);
+
+        if (attenuation <= 0.0 || n_dot_l <= 0.0) {
+            continue;
+        }
+
+        vec3 halfway = normalize(light_dir + view_dir);
+        vec3 radiance = light.color * light.intensity * attenuation * shadow_calculations(light, norm);
+        float diff = n_dot_l * uniforms.diff_strength;
+        float spec = pow(max(dot(norm, halfway), 0.0), float(uniforms.spec_power)) * uniforms.spec_strength;
+
+        diffuse += diff * radiance;
+        specular += spec * radiance;
+    }
+}
+
+mat3 tangent_frame(vec3 norm) {
+    vec3 t = tangent.xyz - norm * dot(norm, tangent.xyz);
+
+    if (dot(t, t) < 0.000001) {
+        t = abs(norm.x) < 0.9 ? cross(norm, vec3(1.0, 0.0, 0.0)) : cross(norm, vec3(0.0, 1.0, 0.0));
+    }
+
+    t = normalize(t);
+
+    return mat3(t, cross(norm, t) * (tangent.w < 0.0 ? -1.0 : 1.0), norm);
+}
+
+vec3 surface_normal(vec3 norm) {
+    if ((uniforms.textures & NORMAL_TEXTURE) == 0u) {
+        return norm;
+    }
 
-        float dist = length(f_pos_dif);
-        float attenuation = 1.0 / (light.attenuation * pow(dist, 2));
-        float edge_softness = 1.0;
+    vec3 sampled = texture(normal_tex, tex_coord).xyz * 2.0 - 1.0;
 
-        if (light.directional) {
-          float theta = dot(light_dir, -normalize(vec3(light.rotation * vec4(0.0, 0.0, 1.0, 1.0))));
+    sampled.xy *= uniforms.normal_scale;
 
-          if (theta > light.outer_cutoff) {
-            float epsilon = light.cutoff - light.outer_cutoff;
-            
-            edge_softness = clamp((theta - light.outer_cutoff) / epsilon, 0.0, 1.0);
-          } else {
+    return normalize(tangent_frame(norm) * sampled);
+}
+
+float distribution_ggx(float n_dot_h, float roughness) {
+    float a = roughness * roughness;
+    float a2 = a * a;
+    float denom = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+
+    return a2 / (PI * denom * denom);
+}
+
+float geometry_smith(float n_dot_v, float n_dot_l, float roughness) {
+    float k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+    float view = n_dot_v / (n_dot_v * (1.0 - k) + k);
+    float light = n_dot_l / (n_dot_l * (1.0 - k) + k);
+
+    return view * light;
+}
+
+vec3 fresnel_schlick(float cos_theta, vec3 f0) {
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+vec3 pbr_calculations(vec3 norm, vec3 geometric, vec3 albedo) {
+    float metallic = uniforms.metallic;
+    float roughness = uniforms.roughness;
+    float occlusion = 1.0;
+    vec3 emissive = uniforms.emissive;
+
+    if ((uniforms.textures & METALLIC_TEXTURE) != 0u) {
+        metallic *= texture(metallic_tex, tex_coord).b;
+    }
+
+    if ((uniforms.textures & ROUGHNESS_TEXTURE) != 0u) {
+        roughness *= texture(roughness_tex, tex_coord).g;
+    }
+
+    if ((uniforms.textures & OCCLUSION_TEXTURE) != 0u) {
+        occlusion = mix(1.0, texture(occlusion_tex, tex_coord).r, uniforms.occlusion_strength);
+    }
+
+    if ((uniforms.textures & EMISSIVE_TEXTURE) != 0u) {
+        emissive *= texture(emissive_tex, tex_coord).rgb;
+    }
+
+    roughness = clamp(roughness, 0.04, 1.0);
+
+    vec3 view_dir = normalize(uniforms.camera_position - f_pos.xyz);
+    vec3 f0 = mix(vec3(0.04), albedo, metallic);
+    float n_dot_v = max(dot(norm, view_dir), 0.0001);
+    vec3 color = vec3(uniforms.ambient) * albedo * occlusion;
+
+    for (uint i = 0; i < uniforms.lights.len; i++) {
+        Light light = uniforms.lights.array[i];
+
+        vec3 light_dir;
+        float attenuation = light_attenuation(light, light_dir);
+        float n_dot_l = dot(norm, light_dir);
+
+        if (attenuation <= 0.0 || n_dot_l <= 0.0) {
             continue;
-          }
         }
 
-        float diff = max(dot(norm, light_dir), 0.0) * uniforms.diff_strength;
+        vec3 halfway = normalize(light_dir + view_dir);
+        vec3 radiance = light.color * light.intensity * attenuation * shadow_calculations(light, geometric);
+        float ndf = distribution_ggx(max(dot(norm, halfway), 0.0), roughness);
+        float g = geometry_smith(n_dot_v, n_dot_l, roughness);
+        vec3 f = fresnel_schlick(max(dot(halfway, view_dir), 0.0), f0);
+        vec3 specular = ndf * g * f / (4.0 * n_dot_v * n_dot_l + 0.0001);
+        vec3 k_d = (vec3(1.0) - f) * (1.0 - metallic);
 
-        brightness += diff * light.intensity * vec4(light.color, 1.0) * attenuation * edge_softness;
+        color += (k_d * albedo / PI + specular) * radiance * n_dot_l;
     }
 
-    return brightness;
+    return color + emissive;
 }
 
 void main() {
     vec4 tex_color = texture(tex, tex_coord) * uniforms.color;
 
+    if (uniforms.pbr) {
+      tex_color *= uniforms.base_color;
+    }
+
     f_color = tex_color;
 
     if (uniforms.lit) {
-      vec3 norm = normalize((inverse(global_rotation) * vec4(normal, 1.0)).xyz);
+      vec3 geometric = normalize(normal);
+      vec3 norm = surface_normal(geometric);
+
+      if (uniforms.pbr) {
+        f_color = vec4(pbr_calculations(norm, geometric, tex_color.rgb), tex_color.a);
+      } else {
+        vec3 diffuse;
+        vec3 specular;
 
-      vec4 brightness = light_calculations(norm);
+        light_calculations(norm, diffuse, specular);
 
-      f_color *= vec4(brightness.xyz, 1.0);
+        f_color = vec4(tex_color.rgb * diffuse + specular, tex_color.a);
+      }
     }
 }
@@ -1,4 +1,10 @@
 pub const MAX_LIGHTS: usize = 256;
+pub const SHADOW_ATLAS_TILES: u32 = 4;
+pub const METALLIC_TEXTURE: u32 = 1;
+pub const ROUGHNESS_TEXTURE: u32 = 2;
+pub const NORMAL_TEXTURE: u32 = 4;
+pub const OCCLUSION_TEXTURE: u32 = 8;
+pub const EMISSIVE_TEXTURE: u32 = 16;
 
 vulkano_shaders::shader! {
     ty: "fragment",
@@ -1,4 +1,8 @@
+pub mod clear_fragment;
+pub mod clear_vertex;
 pub mod fragment;
+pub mod shadow_fragment;
+pub mod shadow_vertex;
 pub mod vertex;
 
 use crate::error::Error;
@@ -8,13 +12,21 @@ use vulkano::device::Device;
 pub struct Shaders {
     pub vertex: vertex::Shader,
     pub fragment: fragment::Shader,
+    pub clear_vertex: clear_vertex::Shader,
+    pub clear_fragment: clear_fragment::Shader,
+    pub shadow_vertex: shadow_vertex::Shader,
+    pub shadow_fragment: shadow_fragment::Shader,
 }
 
 impl Shaders {
     pub fn new(device: Arc<Device>) -> Result<Arc<Self>, Error> {
         Ok(Arc::new(Self {
             vertex: vertex::Shader::load(device.clone())?,
-            fragment: fragment::Shader::load(device)?,
+            fragment: fragment::Shader::load(device.clone())?,
+            clear_vertex: clear_vertex::Shader::load(device.clone())?,
+            clear_fragment: clear_fragment::Shader::load(device.clone())?,
+            shadow_vertex: shadow_vertex::Shader::load(device.clone())?,
+            shadow_fragment: shadow_fragment::Shader::load(device)?,
         }))
     }
 }
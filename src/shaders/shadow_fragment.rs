@@ -0,0 +1,4 @@
+vulkano_shaders::shader! {
+    ty: "fragment",
+    path: "src/shaders/shadow_fragment.glsl"
+}
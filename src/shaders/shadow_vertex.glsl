@@ -0,0 +1,11 @@
+#version 450
+
+layout(location = 0) in vec3 position;
+
+layout(push_constant) uniform Shadow {
+    mat4 matrix;
+} shadow;
+
+void main() {
+    gl_Position = shadow.matrix * vec4(position, 1.0);
+}
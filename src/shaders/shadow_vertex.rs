@@ -0,0 +1,4 @@
+vulkano_shaders::shader! {
+    ty: "vertex",
+    path: "src/shaders/shadow_vertex.glsl"
+}
@@ -0,0 +1,243 @@
+use crate::{
+    assets::mesh::Vertex,
+    bounds::Frustum,
+    components::{Light, Model, Transform},
+    ecs::Entity,
+    error::Error,
+    shaders::{fragment::SHADOW_ATLAS_TILES, shadow_vertex, Shaders},
+};
+use cgmath::Matrix4;
+use std::sync::Arc;
+use vulkano::{
+    buffer::TypedBufferAccess,
+    command_buffer::{
+        pool::standard::StandardCommandPoolBuilder, AutoCommandBufferBuilder,
+        PrimaryAutoCommandBuffer, SubpassContents,
+    },
+    device::{Device, DeviceOwned},
+    format::Format,
+    image::{attachment::AttachmentImage, view::ImageView, ImageAccess, ImageUsage},
+    pipeline::{
+        depth_stencil::DepthStencil, vertex::BuffersDefinition, viewport::Viewport,
+        GraphicsPipeline,
+    },
+    render_pass::{Framebuffer, FramebufferAbstract, RenderPass, Subpass},
+    sampler::{Filter, MipmapMode, Sampler, SamplerAddressMode},
+};
+
+pub const SHADOW_ATLAS_SIZE: u32 = 4096;
+pub const SHADOW_FORMAT: Format = Format::D32_SFLOAT;
+pub const MAX_SHADOWS: u32 = SHADOW_ATLAS_TILES * SHADOW_ATLAS_TILES;
+
+// The atlas starts out as a single texel so the descriptor set always has something to bind,
+// and only grows to `size` once a light actually casts shadows.
+pub struct ShadowAtlas {
+    pub size: u32,
+    pub image: Arc<ImageView<Arc<AttachmentImage>>>,
+    pub sampler: Arc<Sampler>,
+    pub render_pass: Arc<RenderPass>,
+    pub framebuffer: Arc<dyn FramebufferAbstract + Send + Sync>,
+    pub pipeline: Arc<GraphicsPipeline>,
+}
+
+impl ShadowAtlas {
+    pub fn new(device: Arc<Device>, shaders: Arc<Shaders>, size: u32) -> Result<Self, Error> {
+        let image = Self::create_image(device.clone(), 1)?;
+        let sampler = Sampler::new(
+            device.clone(),
+            Filter::Nearest,
+            Filter::Nearest,
+            MipmapMode::Nearest,
+            SamplerAddressMode::ClampToEdge,
+            SamplerAddressMode::ClampToEdge,
+            SamplerAddressMode::ClampToEdge,
+            0.0,
+            1.0,
+            0.0,
+            0.0,
+        )?;
+        let render_pass = Arc::new(vulkano::single_pass_renderpass!(device.clone(),
+            attachments: {
+                depth: {
+                    load: Clear,
+                    store: Store,
+                    format: SHADOW_FORMAT,
+                    samples: 1,
+                }
+            },
+            pass:
+            {
+                color: [],
+                depth_stencil: {depth}
+            }
+        )?);
+        let framebuffer = Self::create_framebuffer(render_pass.clone(), image.clone())?;
+        let pipeline = Arc::new(
+            GraphicsPipeline::start()
+                .vertex_input(BuffersDefinition::new().vertex::<Vertex>())
+                .vertex_shader(shaders.shadow_vertex.main_entry_point(), ())
+                .triangle_list()
+                .viewports_dynamic_scissors_irrelevant(1)
+                .fragment_shader(shaders.shadow_fragment.main_entry_point(), ())
+                .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+                .depth_stencil(DepthStencil::simple_depth_test())
+                .build(device)?,
+        );
+
+        Ok(Self {
+            size,
+            image,
+            sampler,
+            render_pass,
+            framebuffer,
+            pipeline,
+        })
+    }
+
+    fn create_image(
+        device: Arc<Device>,
+        size: u32,
+    ) -> Result<Arc<ImageView<Arc<AttachmentImage>>>, Error> {
+        let image = ImageView::new(AttachmentImage::with_usage(
+            device,
+            [size, size],
+            SHADOW_FORMAT,
+            ImageUsage {
+                depth_stencil_attachment: true,
+                sampled: true,
+                ..ImageUsage::none()
+            },
+        )?)?;
+
+        Ok(image)
+    }
+
+    fn create_framebuffer(
+        render_pass: Arc<RenderPass>,
+        image: Arc<ImageView<Arc<AttachmentImage>>>,
+    ) -> Result<Arc<dyn FramebufferAbstract + Send + Sync>, Error> {
+        let framebuffer = Arc::new(Framebuffer::start(render_pass).add(image)?.build()?);
+
+        Ok(framebuffer)
+    }
+
+    fn allocate(&mut self) -> Result<(), Error> {
+        if ImageView::image(&self.image).dimensions().width_height() != [self.size, self.size] {
+            self.image = Self::create_image(self.render_pass.device().clone(), self.size)?;
+            self.framebuffer =
+                Self::create_framebuffer(self.render_pass.clone(), self.image.clone())?;
+        }
+
+        Ok(())
+    }
+
+    pub fn tile(&self, index: u32) -> Viewport {
+        let size = (self.size / SHADOW_ATLAS_TILES) as f32;
+
+        Viewport {
+            origin: [
+                (index % SHADOW_ATLAS_TILES) as f32 * size,
+                (index / SHADOW_ATLAS_TILES) as f32 * size,
+            ],
+            dimensions: [size, size],
+            depth_range: 0.0..1.0,
+        }
+    }
+
+    fn draw_casters(
+        &self,
+        entity: &Arc<Entity>,
+        matrix: &Matrix4<f32>,
+        builder: &mut AutoCommandBufferBuilder<
+            PrimaryAutoCommandBuffer,
+            StandardCommandPoolBuilder,
+        >,
+    ) -> Result<(), Error> {
+        let frustum = Frustum::from_matrix(matrix);
+
+        for (entity, model) in entity.query::<&Model>() {
+            let data = model.data.read().unwrap();
+
+            if !data.visible || !data.casts_shadows {
+                continue;
+            }
+
+            let inside = model
+                .world_bounds()
+                .map(|(aabb, sphere)| {
+                    frustum.intersects_sphere(&sphere) && frustum.intersects_aabb(&aabb)
+                })
+                .unwrap_or(true);
+
+            if let (true, Some(transform)) = (inside, entity.first_of::<Transform>()) {
+                builder
+                    .push_constants(
+                        self.pipeline.layout().clone(),
+                        0,
+                        shadow_vertex::ty::Shadow {
+                            matrix: (matrix * transform.world_matrix()).into(),
+                        },
+                    )
+                    .bind_vertex_buffers(0, data.mesh.vertices.clone())
+                    .bind_index_buffer(data.mesh.indices.clone())
+                    .draw_indexed(data.mesh.indices.len() as u32, 1, 0, 0, 0)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn draw(
+        &mut self,
+        entity: &Arc<Entity>,
+        lights: &[Arc<Light>],
+        builder: &mut AutoCommandBufferBuilder<
+            PrimaryAutoCommandBuffer,
+            StandardCommandPoolBuilder,
+        >,
+    ) -> Result<Vec<Option<u32>>, Error> {
+        if !lights
+            .iter()
+            .any(|light| light.data.read().unwrap().shadows)
+        {
+            return Ok(vec![None; lights.len()]);
+        }
+
+        self.allocate()?;
+
+        let mut shadows = Vec::new();
+        let mut next = 0;
+
+        builder.begin_render_pass(
+            self.framebuffer.clone(),
+            SubpassContents::Inline,
+            vec![1.0_f32.into()],
+        )?;
+        builder.bind_pipeline_graphics(self.pipeline.clone());
+
+        for light in lights {
+            let matrix = if light.data.read().unwrap().shadows && next < MAX_SHADOWS {
+                light.shadow_matrix()
+            } else {
+                None
+            };
+
+            match matrix {
+                Some(matrix) => {
+                    builder.set_viewport(0, [self.tile(next)]);
+
+                    self.draw_casters(entity, &matrix, builder)?;
+
+                    shadows.push(Some(next));
+                    next += 1;
+                }
+
+                None => shadows.push(None),
+            }
+        }
+
+        builder.end_render_pass()?;
+
+        Ok(shadows)
+    }
+}
@@ -0,0 +1,27 @@
+pub struct Time {
+    pub delta: f32,
+    pub elapsed: f32,
+    pub frame: u64,
+}
+
+impl Time {
+    pub fn new() -> Self {
+        Self {
+            delta: 0.0,
+            elapsed: 0.0,
+            frame: 0,
+        }
+    }
+
+    pub fn advance(&mut self, delta: f32) {
+        self.delta = delta;
+        self.elapsed += delta;
+        self.frame += 1;
+    }
+}
+
+impl Default for Time {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,80 @@
+//! Golden image tests. The renders need a Vulkan device, so they are ignored by default:
+//!
+//!     cargo test --test golden -- --ignored
+//!
+//! Each scene in `tests/golden/*.json` is compared against the PNG of the same name. Set
+//! `WRENCH_UPDATE_GOLDEN=1` to (re)write the references.
+
+use std::{env, f32::consts::FRAC_PI_4, fs::File, io::BufReader, path::PathBuf};
+use wrench::{
+    components::Camera,
+    ecs::{self, Entity, Registry},
+    error::Error,
+    golden::Golden,
+    serialization::SceneDescription,
+    HeadlessEngine, SampleCount, Scene, Vector4,
+};
+
+const SCENES: [&str; 2] = ["lit_cube", "unlit_cube"];
+
+fn directory() -> PathBuf {
+    PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden"))
+}
+
+fn golden() -> Golden {
+    let mut golden = Golden::new(directory(), concat!(env!("CARGO_TARGET_TMPDIR"), "/golden"));
+
+    golden.update = env::var_os("WRENCH_UPDATE_GOLDEN").is_some();
+    golden
+}
+
+fn engine() -> Result<HeadlessEngine, Error> {
+    let instance = HeadlessEngine::instance()?;
+    let root = Entity::new(ecs::id("root"));
+    let camera = Camera::new(ecs::id("camera"), FRAC_PI_4, 0.1, 100.0);
+
+    root.add(&camera);
+
+    let placeholder = Scene::new(&root, camera, Vector4::new(0.0, 0.0, 0.0, 1.0));
+
+    HeadlessEngine::first(instance, placeholder, [128, 128], SampleCount::Sample1)
+}
+
+fn check(name: &str) {
+    let mut engine = engine().expect("no Vulkan device available");
+    let comparison = golden()
+        .check_scene(
+            &mut engine,
+            name,
+            directory().join(format!("{}.json", name)),
+            &Registry::new(),
+        )
+        .unwrap();
+
+    assert!(
+        comparison.passed,
+        "{}: {} pixels differ (max difference {})",
+        name, comparison.mismatched, comparison.max_difference
+    );
+}
+
+#[test]
+fn scenes_parse() {
+    for name in SCENES {
+        let file = File::open(directory().join(format!("{}.json", name))).unwrap();
+
+        serde_json::from_reader::<_, SceneDescription>(BufReader::new(file)).unwrap();
+    }
+}
+
+#[test]
+#[ignore = "needs a Vulkan device"]
+fn unlit_cube() {
+    check("unlit_cube");
+}
+
+#[test]
+#[ignore = "needs a Vulkan device"]
+fn lit_cube() {
+    check("lit_cube");
+}
@@ -0,0 +1,48 @@
+use crate::Component;
+use std::any::TypeId;
+
+#[derive(Debug, Clone, Default)]
+pub struct Access {
+    pub reads: Vec<TypeId>,
+    pub writes: Vec<TypeId>,
+    pub exclusive: bool,
+}
+
+impl Access {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn exclusive() -> Self {
+        Self {
+            exclusive: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn read<C>(mut self) -> Self
+    where
+        C: Component,
+    {
+        self.reads.push(TypeId::of::<C>());
+        self
+    }
+
+    pub fn write<C>(mut self) -> Self
+    where
+        C: Component,
+    {
+        self.writes.push(TypeId::of::<C>());
+        self
+    }
+
+    pub fn conflicts(&self, other: &Self) -> bool {
+        self.exclusive
+            || other.exclusive
+            || self
+                .writes
+                .iter()
+                .any(|t| other.writes.contains(t) || other.reads.contains(t))
+            || other.writes.iter().any(|t| self.reads.contains(t))
+    }
+}
@@ -1,4 +1,8 @@
-use crate::Entity;
+use crate::{
+    reflect::{Field, ReflectError},
+    Entity,
+};
+use serde_json::Value;
 use std::{
     any::Any,
     sync::{Arc, RwLock},
@@ -13,6 +17,18 @@ pub trait Component: Send + Sync + 'static {
 
     fn as_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync + 'static>;
 
+    fn fields(&self) -> Vec<Field> {
+        Vec::new()
+    }
+
+    fn get_field(&self, _: &str) -> Option<Value> {
+        None
+    }
+
+    fn set_field(&self, name: &str, _: Value) -> Result<(), ReflectError> {
+        Err(ReflectError::UnknownField(name.to_string()))
+    }
+
     fn on_init(&self) {}
 
     fn on_update(&self) {}
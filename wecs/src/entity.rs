@@ -1,4 +1,4 @@
-use crate::{self as ecs, Component};
+use crate::{self as ecs, Component, Query};
 use std::{
     any::Any,
     sync::{Arc, RwLock},
@@ -27,6 +27,10 @@ impl Entity {
     where
         C: Component,
     {
+        self.add_dyn(component.clone());
+    }
+
+    pub fn add_dyn(self: &Arc<Self>, component: Arc<dyn Component>) {
         {
             let entity = component.entity().read().unwrap().clone();
 
@@ -37,16 +41,24 @@ impl Entity {
 
         *component.entity().write().unwrap() = Some(self.clone());
 
-        let mut components = self.components.write().unwrap();
+        self.components.write().unwrap().push(component);
+    }
+
+    pub(crate) fn attach(self: &Arc<Self>, component: Arc<dyn Component>) {
+        let owner = component.entity();
+
+        if owner.read().unwrap().is_none() {
+            *owner.write().unwrap() = Some(self.clone());
+        }
 
-        components.push(component.clone());
+        self.components.write().unwrap().push(component);
     }
 
     pub fn add_all<C>(self: &Arc<Self>, components: &[&Arc<C>])
     where
         C: Component,
     {
-        components.into_iter().for_each(|c| {
+        components.iter().for_each(|c| {
             self.add(c);
         });
     }
@@ -59,35 +71,19 @@ impl Entity {
     where
         C: Component,
     {
-        match self
-            .components
+        self.components
             .read()
             .unwrap()
             .iter()
-            .filter_map(|c| {
-                if *c.id() == *id && c.tid() == tid {
-                    return Some(c);
-                }
-
-                None
-            })
-            .next()
-        {
-            Some(component) => Some(component.clone().as_any().downcast::<C>().unwrap()),
-
-            None => None,
-        }
+            .find(|c| *c.id() == *id && c.tid() == tid)
+            .and_then(|c| c.clone().as_any().downcast::<C>().ok())
     }
 
     pub fn get_first<C>(&self, tid: Arc<String>) -> Option<Arc<C>>
     where
         C: Component,
     {
-        match self.get_type::<C>(tid).first() {
-            Some(component) => Some(component.clone()),
-
-            None => None,
-        }
+        self.get_type::<C>(tid).first().cloned()
     }
 
     pub fn get_type<C>(&self, tid: Arc<String>) -> Vec<Arc<C>>
@@ -98,12 +94,61 @@ impl Entity {
             .read()
             .unwrap()
             .iter()
-            .filter_map(|c| {
-                if *c.tid() == *tid {
-                    return Some(c.clone().as_any().downcast::<C>().unwrap());
-                }
+            .filter(|c| *c.tid() == *tid)
+            .filter_map(|c| c.clone().as_any().downcast::<C>().ok())
+            .collect()
+    }
+
+    pub fn first_of<C>(&self) -> Option<Arc<C>>
+    where
+        C: Component,
+    {
+        self.components
+            .read()
+            .unwrap()
+            .iter()
+            .find_map(|c| c.clone().as_any().downcast::<C>().ok())
+    }
+
+    pub fn of_type<C>(&self) -> Vec<Arc<C>>
+    where
+        C: Component,
+    {
+        self.components
+            .read()
+            .unwrap()
+            .iter()
+            .filter_map(|c| c.clone().as_any().downcast::<C>().ok())
+            .collect()
+    }
+
+    pub fn children(&self) -> Vec<Arc<Entity>> {
+        self.of_type::<Entity>()
+    }
+
+    // Depth-first, starting with (and including) this entity itself.
+    pub fn descendants(self: &Arc<Self>) -> Vec<Arc<Entity>> {
+        let mut entities = Vec::new();
+        let mut stack = vec![self.clone()];
 
-                None
+        while let Some(entity) = stack.pop() {
+            stack.extend(entity.children().into_iter().rev());
+            entities.push(entity);
+        }
+
+        entities
+    }
+
+    pub fn query<Q>(self: &Arc<Self>) -> Vec<(Arc<Entity>, Q::Item)>
+    where
+        Q: Query,
+    {
+        self.descendants()
+            .into_iter()
+            .flat_map(|entity| {
+                Q::fetch(entity.as_ref())
+                    .into_iter()
+                    .map(move |item| (entity.clone(), item))
             })
             .collect()
     }
@@ -128,7 +173,7 @@ impl Entity {
     where
         C: Component,
     {
-        components.into_iter().for_each(|c| {
+        components.iter().for_each(|c| {
             self.remove(c);
         });
     }
@@ -167,3 +212,96 @@ impl Component for Entity {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Tag {
+        id: Arc<String>,
+        entity: Arc<RwLock<Option<Arc<Entity>>>>,
+    }
+
+    impl Tag {
+        fn new(id: &str) -> Arc<Self> {
+            Arc::new(Self {
+                id: ecs::id(id),
+                entity: ecs::entity(None),
+            })
+        }
+    }
+
+    impl Component for Tag {
+        fn entity(&self) -> Arc<RwLock<Option<Arc<Entity>>>> {
+            self.entity.clone()
+        }
+
+        fn id(&self) -> Arc<String> {
+            self.id.clone()
+        }
+
+        fn tid(&self) -> Arc<String> {
+            ecs::id("tag")
+        }
+
+        fn as_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync + 'static> {
+            self
+        }
+    }
+
+    #[test]
+    fn query_yields_every_component_of_a_type() {
+        let root = Entity::new(ecs::id("root"));
+        let child = Entity::new(ecs::id("child"));
+
+        child.add(&Tag::new("a"));
+        child.add(&Tag::new("b"));
+        root.add(&child);
+
+        let ids = root
+            .query::<&Tag>()
+            .into_iter()
+            .map(|(entity, tag)| (entity.id.to_string(), tag.id.to_string()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            ids,
+            vec![
+                ("child".to_string(), "a".to_string()),
+                ("child".to_string(), "b".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn tuple_queries_yield_every_combination() {
+        let root = Entity::new(ecs::id("root"));
+
+        root.add(&Tag::new("a"));
+        root.add(&Tag::new("b"));
+
+        assert_eq!(root.query::<(&Tag, &Tag)>().len(), 4);
+        assert_eq!(root.query::<(&Tag, Option<&Entity>)>().len(), 2);
+        assert!(root.query::<(&Tag, &Entity)>().is_empty());
+    }
+
+    #[test]
+    fn query_includes_the_root_entity() {
+        let root = Entity::new(ecs::id("root"));
+        let child = Entity::new(ecs::id("child"));
+
+        root.add(&Tag::new("root"));
+        child.add(&Tag::new("child"));
+        root.add(&child);
+
+        let descendants = root
+            .descendants()
+            .into_iter()
+            .map(|entity| entity.id.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(descendants, vec!["root", "child"]);
+        assert_eq!(root.query::<&Tag>().len(), 2);
+        assert_eq!(child.query::<&Tag>().len(), 1);
+    }
+}
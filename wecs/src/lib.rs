@@ -1,8 +1,23 @@
+pub mod access;
 pub mod component;
 pub mod entity;
+pub mod query;
+pub mod reflect;
+pub mod registry;
+pub mod schedule;
+pub mod system;
+pub mod world;
 
+pub use access::Access;
 pub use component::Component;
 pub use entity::{Entity, ENTITY_ID};
+pub use query::Query;
+pub use reflect::{Field, Reflect, ReflectError};
+pub use registry::{Constructor, Registry};
+pub use schedule::{Execution, Schedule, ScheduleError, Stage};
+pub use serde_json::Value;
+pub use system::{ComponentUpdates, System, COMPONENT_UPDATES_ID};
+pub use world::{EntityId, World, WorldError};
 
 use std::sync::{Arc, RwLock};
 
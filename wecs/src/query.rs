@@ -0,0 +1,123 @@
+use crate::{Component, Entity};
+use std::sync::Arc;
+
+pub trait Source {
+    fn of_type<C>(&self) -> Vec<Arc<C>>
+    where
+        C: Component;
+
+    fn first_of<C>(&self) -> Option<Arc<C>>
+    where
+        C: Component,
+    {
+        self.of_type::<C>().into_iter().next()
+    }
+}
+
+impl Source for Entity {
+    fn of_type<C>(&self) -> Vec<Arc<C>>
+    where
+        C: Component,
+    {
+        Entity::of_type(self)
+    }
+
+    fn first_of<C>(&self) -> Option<Arc<C>>
+    where
+        C: Component,
+    {
+        Entity::first_of(self)
+    }
+}
+
+pub trait Query {
+    type Item;
+
+    fn fetch<S>(source: &S) -> Vec<Self::Item>
+    where
+        S: Source;
+}
+
+impl<C> Query for &C
+where
+    C: Component,
+{
+    type Item = Arc<C>;
+
+    fn fetch<S>(source: &S) -> Vec<Self::Item>
+    where
+        S: Source,
+    {
+        source.of_type::<C>()
+    }
+}
+
+impl<Q> Query for Option<Q>
+where
+    Q: Query,
+{
+    type Item = Option<Q::Item>;
+
+    fn fetch<S>(source: &S) -> Vec<Self::Item>
+    where
+        S: Source,
+    {
+        let items = Q::fetch(source);
+
+        if items.is_empty() {
+            vec![None]
+        } else {
+            items.into_iter().map(Some).collect()
+        }
+    }
+}
+
+impl Query for () {
+    type Item = ();
+
+    fn fetch<S>(_: &S) -> Vec<Self::Item>
+    where
+        S: Source,
+    {
+        vec![()]
+    }
+}
+
+macro_rules! impl_query {
+    ($head:ident $(, $tail:ident)*) => {
+        impl<$head $(, $tail)*> Query for ($head, $($tail,)*)
+        where
+            $head: Query,
+            $head::Item: Clone,
+            $($tail: Query, $tail::Item: Clone,)*
+        {
+            type Item = ($head::Item, $($tail::Item,)*);
+
+            #[allow(non_snake_case)]
+            fn fetch<S>(source: &S) -> Vec<Self::Item>
+            where
+                S: Source,
+            {
+                let tail = <($($tail,)*) as Query>::fetch(source);
+                let mut items = Vec::new();
+
+                for $head in $head::fetch(source) {
+                    for ($($tail,)*) in tail.iter().cloned() {
+                        items.push(($head.clone(), $($tail,)*));
+                    }
+                }
+
+                items
+            }
+        }
+    };
+}
+
+impl_query!(A);
+impl_query!(A, B);
+impl_query!(A, B, C);
+impl_query!(A, B, C, D);
+impl_query!(A, B, C, D, E);
+impl_query!(A, B, C, D, E, F);
+impl_query!(A, B, C, D, E, F, G);
+impl_query!(A, B, C, D, E, F, G, H);
@@ -0,0 +1,60 @@
+use crate::Component;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub name: &'static str,
+    pub ty: &'static str,
+}
+
+impl Field {
+    pub fn new(name: &'static str, ty: &'static str) -> Self {
+        Self { name, ty }
+    }
+}
+
+#[derive(Debug)]
+pub enum ReflectError {
+    UnknownComponent(String),
+    UnknownField(String),
+    InvalidValue(String, serde_json::Error),
+}
+
+pub trait Reflect {
+    fn fields(&self) -> Vec<Field>;
+
+    fn get_field(&self, name: &str) -> Option<Value>;
+
+    fn set_field(&mut self, name: &str, value: Value) -> Result<(), ReflectError>;
+}
+
+pub fn to_value<T>(value: &T) -> Option<Value>
+where
+    T: Serialize,
+{
+    serde_json::to_value(value).ok()
+}
+
+pub fn from_value<T>(name: &str, value: Value) -> Result<T, ReflectError>
+where
+    T: DeserializeOwned,
+{
+    serde_json::from_value(value).map_err(|e| ReflectError::InvalidValue(name.to_string(), e))
+}
+
+pub fn values(component: &dyn Component) -> Map<String, Value> {
+    component
+        .fields()
+        .into_iter()
+        .filter_map(|field| Some((field.name.to_string(), component.get_field(field.name)?)))
+        .collect()
+}
+
+pub fn apply(component: &dyn Component, values: &Map<String, Value>) -> Result<(), ReflectError> {
+    for (name, value) in values {
+        component.set_field(name, value.clone())?;
+    }
+
+    Ok(())
+}
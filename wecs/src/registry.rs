@@ -0,0 +1,65 @@
+use crate::{
+    reflect::{self, ReflectError},
+    Component,
+};
+use serde_json::{Map, Value};
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+pub type Constructor = Arc<dyn Fn(Arc<String>) -> Arc<dyn Component> + Send + Sync>;
+
+pub struct Registry {
+    constructors: RwLock<HashMap<String, Constructor>>,
+}
+
+impl Registry {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            constructors: RwLock::new(HashMap::new()),
+        })
+    }
+
+    pub fn register<F>(&self, tid: &str, constructor: F)
+    where
+        F: Fn(Arc<String>) -> Arc<dyn Component> + Send + Sync + 'static,
+    {
+        self.constructors
+            .write()
+            .unwrap()
+            .insert(tid.to_string(), Arc::new(constructor));
+    }
+
+    pub fn unregister(&self, tid: &str) -> bool {
+        self.constructors.write().unwrap().remove(tid).is_some()
+    }
+
+    pub fn contains(&self, tid: &str) -> bool {
+        self.constructors.read().unwrap().contains_key(tid)
+    }
+
+    pub fn tids(&self) -> Vec<String> {
+        self.constructors.read().unwrap().keys().cloned().collect()
+    }
+
+    pub fn construct(
+        &self,
+        tid: &str,
+        id: Arc<String>,
+        values: &Map<String, Value>,
+    ) -> Result<Arc<dyn Component>, ReflectError> {
+        let constructor = self
+            .constructors
+            .read()
+            .unwrap()
+            .get(tid)
+            .cloned()
+            .ok_or_else(|| ReflectError::UnknownComponent(tid.to_string()))?;
+        let component = constructor(id);
+
+        reflect::apply(component.as_ref(), values)?;
+
+        Ok(component)
+    }
+}
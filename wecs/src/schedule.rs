@@ -0,0 +1,440 @@
+use crate::{Entity, System};
+use rayon::prelude::*;
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Stage {
+    PreUpdate,
+    Update,
+    PostUpdate,
+    RenderPrep,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 4] = [
+        Stage::PreUpdate,
+        Stage::Update,
+        Stage::PostUpdate,
+        Stage::RenderPrep,
+    ];
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Execution {
+    Parallel,
+    Sequential,
+}
+
+#[derive(Debug)]
+pub enum ScheduleError {
+    DuplicateSystem(Arc<String>),
+    UnknownSystem(Arc<String>),
+    Cycle(Stage),
+}
+
+pub type Batch = Vec<Arc<dyn System>>;
+
+pub type StageOrder = Vec<(Stage, Vec<Batch>)>;
+
+pub struct SystemEntry {
+    pub stage: Stage,
+    pub system: Arc<dyn System>,
+    pub after: Vec<Arc<String>>,
+    pub before: Vec<Arc<String>>,
+}
+
+pub struct Schedule {
+    pub execution: Execution,
+    systems: Vec<SystemEntry>,
+    order: RwLock<Option<StageOrder>>,
+}
+
+impl Schedule {
+    pub fn new() -> Self {
+        Self {
+            execution: Execution::Parallel,
+            systems: Vec::new(),
+            order: RwLock::new(None),
+        }
+    }
+
+    pub fn add(
+        &mut self,
+        stage: Stage,
+        system: Arc<dyn System>,
+        after: &[&str],
+        before: &[&str],
+    ) -> Result<(), ScheduleError> {
+        let id = system.id();
+
+        if self.systems.iter().any(|e| *e.system.id() == *id) {
+            return Err(ScheduleError::DuplicateSystem(id));
+        }
+
+        self.systems.push(SystemEntry {
+            stage,
+            system,
+            after: after.iter().map(|id| crate::id(id)).collect(),
+            before: before.iter().map(|id| crate::id(id)).collect(),
+        });
+        *self.order.get_mut().unwrap() = None;
+
+        Ok(())
+    }
+
+    pub fn remove(&mut self, id: &str) -> Option<Arc<dyn System>> {
+        let i = self.systems.iter().position(|e| *e.system.id() == id)?;
+
+        *self.order.get_mut().unwrap() = None;
+
+        Some(self.systems.remove(i).system)
+    }
+
+    pub fn systems(&self) -> &[SystemEntry] {
+        &self.systems
+    }
+
+    fn index_in_stage(
+        &self,
+        indices: &HashMap<Arc<String>, usize>,
+        id: &Arc<String>,
+    ) -> Result<Option<usize>, ScheduleError> {
+        match indices.get(id) {
+            Some(&i) => Ok(Some(i)),
+            None if self.systems.iter().any(|e| e.system.id() == *id) => Ok(None),
+            None => Err(ScheduleError::UnknownSystem(id.clone())),
+        }
+    }
+
+    fn sort_stage(&self, stage: Stage) -> Result<Vec<Batch>, ScheduleError> {
+        let entries = self
+            .systems
+            .iter()
+            .filter(|e| e.stage == stage)
+            .collect::<Vec<_>>();
+        let indices = entries
+            .iter()
+            .enumerate()
+            .map(|(i, e)| (e.system.id(), i))
+            .collect::<HashMap<_, _>>();
+        let mut dependents = vec![Vec::new(); entries.len()];
+        let mut dependencies = vec![0; entries.len()];
+
+        for (i, entry) in entries.iter().enumerate() {
+            for after in &entry.after {
+                if let Some(j) = self.index_in_stage(&indices, after)? {
+                    dependents[j].push(i);
+                    dependencies[i] += 1;
+                }
+            }
+
+            for before in &entry.before {
+                if let Some(j) = self.index_in_stage(&indices, before)? {
+                    dependents[i].push(j);
+                    dependencies[j] += 1;
+                }
+            }
+        }
+
+        let accesses = entries
+            .iter()
+            .map(|e| e.system.access())
+            .collect::<Vec<_>>();
+        let mut batches: Vec<Vec<usize>> = Vec::new();
+        let mut sorted = 0;
+        let mut done = vec![false; entries.len()];
+
+        while sorted < entries.len() {
+            let i = (0..entries.len())
+                .find(|&i| !done[i] && dependencies[i] == 0)
+                .ok_or(ScheduleError::Cycle(stage))?;
+
+            done[i] = true;
+            sorted += 1;
+
+            for &j in &dependents[i] {
+                dependencies[j] -= 1;
+            }
+
+            match batches.last_mut() {
+                Some(batch)
+                    if batch.iter().all(|&j| {
+                        !dependents[j].contains(&i) && !accesses[i].conflicts(&accesses[j])
+                    }) =>
+                {
+                    batch.push(i)
+                }
+
+                _ => batches.push(vec![i]),
+            }
+        }
+
+        Ok(batches
+            .into_iter()
+            .map(|batch| {
+                batch
+                    .into_iter()
+                    .map(|i| entries[i].system.clone())
+                    .collect()
+            })
+            .collect())
+    }
+
+    pub fn order(&self) -> Result<StageOrder, ScheduleError> {
+        if let Some(order) = &*self.order.read().unwrap() {
+            return Ok(order.clone());
+        }
+
+        let order = Stage::ALL
+            .iter()
+            .map(|&stage| Ok((stage, self.sort_stage(stage)?)))
+            .collect::<Result<Vec<_>, ScheduleError>>()?;
+
+        *self.order.write().unwrap() = Some(order.clone());
+
+        Ok(order)
+    }
+
+    pub fn run(&self, root: &Arc<Entity>) -> Result<(), ScheduleError> {
+        for (_, batches) in self.order()? {
+            for batch in batches {
+                match self.execution {
+                    Execution::Parallel if batch.len() > 1 => batch
+                        .par_iter()
+                        .for_each(|system| system.run_parallel(root)),
+
+                    Execution::Parallel => {
+                        for system in batch {
+                            system.run_parallel(root);
+                        }
+                    }
+
+                    Execution::Sequential => {
+                        for system in batch {
+                            system.run(root);
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{system::ComponentUpdates, Access, Component};
+    use std::{
+        any::Any,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Mutex,
+        },
+    };
+
+    struct Recorder {
+        id: &'static str,
+        log: Arc<Mutex<Vec<&'static str>>>,
+    }
+
+    impl System for Recorder {
+        fn id(&self) -> Arc<String> {
+            crate::id(self.id)
+        }
+
+        fn run(&self, _: &Arc<Entity>) {
+            self.log.lock().unwrap().push(self.id);
+        }
+    }
+
+    fn schedule(
+        systems: &[(Stage, &'static str, &[&str], &[&str])],
+    ) -> (Schedule, Arc<Mutex<Vec<&'static str>>>) {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut schedule = Schedule::new();
+
+        for &(stage, id, after, before) in systems {
+            let system = Arc::new(Recorder {
+                id,
+                log: log.clone(),
+            });
+
+            schedule.add(stage, system, after, before).unwrap();
+        }
+
+        (schedule, log)
+    }
+
+    #[test]
+    fn systems_run_in_stage_and_dependency_order() {
+        let (schedule, log) = schedule(&[
+            (Stage::Update, "c", &["b"], &[]),
+            (Stage::Update, "a", &[], &["b"]),
+            (Stage::PreUpdate, "pre", &[], &[]),
+            (Stage::Update, "b", &["pre"], &[]),
+        ]);
+
+        schedule.run(&Entity::new(crate::id("root"))).unwrap();
+
+        assert_eq!(*log.lock().unwrap(), vec!["pre", "a", "b", "c"]);
+    }
+
+    struct Writer<C> {
+        id: &'static str,
+        component: std::marker::PhantomData<C>,
+    }
+
+    impl<C> Writer<C> {
+        fn new(id: &'static str) -> Arc<Self> {
+            Arc::new(Self {
+                id,
+                component: std::marker::PhantomData,
+            })
+        }
+    }
+
+    impl<C> System for Writer<C>
+    where
+        C: Component,
+    {
+        fn id(&self) -> Arc<String> {
+            crate::id(self.id)
+        }
+
+        fn access(&self) -> Access {
+            Access::new().write::<C>()
+        }
+
+        fn run(&self, _: &Arc<Entity>) {}
+    }
+
+    struct Counter {
+        id: Arc<String>,
+        entity: Arc<RwLock<Option<Arc<Entity>>>>,
+        updates: Arc<AtomicUsize>,
+    }
+
+    impl Component for Counter {
+        fn entity(&self) -> Arc<RwLock<Option<Arc<Entity>>>> {
+            self.entity.clone()
+        }
+
+        fn id(&self) -> Arc<String> {
+            self.id.clone()
+        }
+
+        fn tid(&self) -> Arc<String> {
+            crate::id("counter")
+        }
+
+        fn as_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync + 'static> {
+            self
+        }
+
+        fn on_update(&self) {
+            self.updates.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn batch_sizes(schedule: &Schedule, stage: Stage) -> Vec<usize> {
+        schedule
+            .order()
+            .unwrap()
+            .into_iter()
+            .find(|(s, _)| *s == stage)
+            .map(|(_, batches)| batches.iter().map(Vec::len).collect())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn disjoint_systems_share_a_batch() {
+        let mut schedule = Schedule::new();
+
+        schedule
+            .add(Stage::Update, Writer::<Counter>::new("counters"), &[], &[])
+            .unwrap();
+        schedule
+            .add(Stage::Update, Writer::<Entity>::new("entities"), &[], &[])
+            .unwrap();
+
+        assert_eq!(batch_sizes(&schedule, Stage::Update), vec![2]);
+
+        schedule
+            .add(
+                Stage::Update,
+                Writer::<Counter>::new("more counters"),
+                &[],
+                &[],
+            )
+            .unwrap();
+
+        assert_eq!(batch_sizes(&schedule, Stage::Update), vec![2, 1]);
+    }
+
+    #[test]
+    fn component_updates_reach_every_component() {
+        let updates = Arc::new(AtomicUsize::new(0));
+        let root = Entity::new(crate::id("root"));
+        let mut parent = root.clone();
+
+        for i in 0..16 {
+            let child = Entity::new(crate::id(&i.to_string()));
+
+            child.add(&Arc::new(Counter {
+                id: crate::id("counter"),
+                entity: crate::entity(None),
+                updates: updates.clone(),
+            }));
+            parent.add(&child);
+            parent = child;
+        }
+
+        let mut schedule = Schedule::new();
+
+        schedule
+            .add(Stage::Update, Arc::new(ComponentUpdates), &[], &[])
+            .unwrap();
+        schedule.run(&root).unwrap();
+
+        assert_eq!(updates.load(Ordering::Relaxed), 16);
+
+        schedule.execution = Execution::Sequential;
+        schedule.run(&root).unwrap();
+
+        assert_eq!(updates.load(Ordering::Relaxed), 32);
+    }
+
+    #[test]
+    fn cycles_are_reported() {
+        let (schedule, _) = schedule(&[
+            (Stage::Update, "a", &["b"], &[]),
+            (Stage::Update, "b", &["a"], &[]),
+        ]);
+
+        assert!(matches!(
+            schedule.order(),
+            Err(ScheduleError::Cycle(Stage::Update))
+        ));
+    }
+
+    #[test]
+    fn unknown_systems_are_reported() {
+        let (schedule, _) = schedule(&[(Stage::Update, "a", &["typo"], &[])]);
+
+        match schedule.order() {
+            Err(ScheduleError::UnknownSystem(id)) => assert_eq!(id.as_str(), "typo"),
+            other => panic!("expected an unknown system error, got {:?}", other.err()),
+        }
+    }
+}
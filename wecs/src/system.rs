@@ -0,0 +1,48 @@
+use crate::{Access, Component, Entity};
+use rayon::prelude::*;
+use std::sync::Arc;
+
+pub const COMPONENT_UPDATES_ID: &str = "component updates";
+
+pub trait System: Send + Sync + 'static {
+    fn id(&self) -> Arc<String>;
+
+    fn access(&self) -> Access {
+        Access::exclusive()
+    }
+
+    fn run(&self, root: &Arc<Entity>);
+
+    fn run_parallel(&self, root: &Arc<Entity>) {
+        self.run(root);
+    }
+}
+
+pub struct ComponentUpdates;
+
+impl System for ComponentUpdates {
+    fn id(&self) -> Arc<String> {
+        crate::id(COMPONENT_UPDATES_ID)
+    }
+
+    fn access(&self) -> Access {
+        Access::exclusive()
+    }
+
+    fn run(&self, root: &Arc<Entity>) {
+        root.on_update();
+    }
+
+    fn run_parallel(&self, root: &Arc<Entity>) {
+        let components = root
+            .descendants()
+            .into_iter()
+            .flat_map(|entity| entity.components().read().unwrap().clone())
+            .filter(|component| component.clone().as_any().downcast::<Entity>().is_err())
+            .collect::<Vec<_>>();
+
+        components
+            .par_iter()
+            .for_each(|component| component.on_update());
+    }
+}
@@ -0,0 +1,611 @@
+use crate::{query::Source, Component, Entity, Query};
+use std::{
+    any::TypeId,
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct EntityId {
+    pub index: u32,
+    pub generation: u32,
+}
+
+#[derive(Debug)]
+pub enum WorldError {
+    // Columns hold one component of each type per entity.
+    DuplicateComponent {
+        entity: Arc<String>,
+        tid: Arc<String>,
+    },
+}
+
+#[derive(Default)]
+struct Column {
+    components: Vec<Arc<dyn Component>>,
+    owners: Vec<EntityId>,
+    sparse: Vec<Option<usize>>,
+}
+
+impl Column {
+    fn dense(&self, id: EntityId) -> Option<usize> {
+        let dense = (*self.sparse.get(id.index as usize)?)?;
+
+        if self.owners[dense] == id {
+            Some(dense)
+        } else {
+            None
+        }
+    }
+
+    fn get(&self, id: EntityId) -> Option<&Arc<dyn Component>> {
+        Some(&self.components[self.dense(id)?])
+    }
+
+    fn insert(&mut self, id: EntityId, component: Arc<dyn Component>) {
+        let index = id.index as usize;
+
+        if self.sparse.len() <= index {
+            self.sparse.resize(index + 1, None);
+        }
+
+        match self.sparse[index] {
+            Some(dense) => {
+                self.components[dense] = component;
+                self.owners[dense] = id;
+            }
+
+            None => {
+                self.sparse[index] = Some(self.components.len());
+                self.components.push(component);
+                self.owners.push(id);
+            }
+        }
+    }
+
+    fn remove(&mut self, id: EntityId) -> Option<Arc<dyn Component>> {
+        let dense = self.dense(id)?;
+
+        self.sparse[id.index as usize] = None;
+
+        let component = self.components.swap_remove(dense);
+
+        self.owners.swap_remove(dense);
+
+        if let Some(moved) = self.owners.get(dense) {
+            self.sparse[moved.index as usize] = Some(dense);
+        }
+
+        Some(component)
+    }
+}
+
+struct Slot {
+    generation: u32,
+    alive: bool,
+    parent: Option<EntityId>,
+    children: Vec<EntityId>,
+    entity: Option<Arc<Entity>>,
+}
+
+#[derive(Default)]
+struct Storage {
+    slots: Vec<Slot>,
+    free: Vec<u32>,
+    columns: HashMap<TypeId, Column>,
+    imported: HashMap<usize, EntityId>,
+}
+
+impl Storage {
+    fn is_alive(&self, id: EntityId) -> bool {
+        self.slots
+            .get(id.index as usize)
+            .map(|slot| slot.alive && slot.generation == id.generation)
+            .unwrap_or(false)
+    }
+
+    fn spawn(&mut self, parent: Option<EntityId>) -> EntityId {
+        let id = match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+
+                slot.alive = true;
+                slot.parent = parent;
+
+                EntityId {
+                    index,
+                    generation: slot.generation,
+                }
+            }
+
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    alive: true,
+                    parent,
+                    children: Vec::new(),
+                    entity: None,
+                });
+
+                EntityId {
+                    index: self.slots.len() as u32 - 1,
+                    generation: 0,
+                }
+            }
+        };
+
+        if let Some(parent) = parent {
+            self.slots[parent.index as usize].children.push(id);
+        }
+
+        id
+    }
+
+    fn insert(&mut self, id: EntityId, component: Arc<dyn Component>) {
+        let type_id = (*component.clone().as_any()).type_id();
+
+        self.columns
+            .entry(type_id)
+            .or_default()
+            .insert(id, component);
+    }
+
+    fn children(&self, id: EntityId) -> Vec<EntityId> {
+        if self.is_alive(id) {
+            self.slots[id.index as usize].children.clone()
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn alive(&self) -> Vec<EntityId> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter(|(_, slot)| slot.alive)
+            .map(|(index, slot)| EntityId {
+                index: index as u32,
+                generation: slot.generation,
+            })
+            .collect()
+    }
+}
+
+struct Row<'a> {
+    storage: &'a Storage,
+    id: EntityId,
+}
+
+impl<'a> Source for Row<'a> {
+    fn of_type<C>(&self) -> Vec<Arc<C>>
+    where
+        C: Component,
+    {
+        self.first_of::<C>().into_iter().collect()
+    }
+
+    fn first_of<C>(&self) -> Option<Arc<C>>
+    where
+        C: Component,
+    {
+        self.storage
+            .columns
+            .get(&TypeId::of::<C>())?
+            .get(self.id)
+            .and_then(|c| c.clone().as_any().downcast::<C>().ok())
+    }
+}
+
+pub struct World {
+    storage: RwLock<Storage>,
+}
+
+impl World {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            storage: RwLock::new(Storage::default()),
+        })
+    }
+
+    pub fn spawn(&self) -> EntityId {
+        self.storage.write().unwrap().spawn(None)
+    }
+
+    pub fn spawn_child(&self, parent: EntityId) -> Option<EntityId> {
+        let mut storage = self.storage.write().unwrap();
+
+        if storage.is_alive(parent) {
+            Some(storage.spawn(Some(parent)))
+        } else {
+            None
+        }
+    }
+
+    pub fn despawn(&self, id: EntityId) -> bool {
+        let mut storage = self.storage.write().unwrap();
+
+        if !storage.is_alive(id) {
+            return false;
+        }
+
+        if let Some(parent) = storage.slots[id.index as usize].parent {
+            storage.slots[parent.index as usize]
+                .children
+                .retain(|child| *child != id);
+        }
+
+        let mut stack = vec![id];
+
+        while let Some(id) = stack.pop() {
+            stack.append(&mut storage.slots[id.index as usize].children);
+
+            for column in storage.columns.values_mut() {
+                column.remove(id);
+            }
+
+            let slot = &mut storage.slots[id.index as usize];
+
+            slot.alive = false;
+            slot.parent = None;
+            slot.generation += 1;
+
+            if let Some(entity) = slot.entity.take() {
+                storage.imported.remove(&(Arc::as_ptr(&entity) as usize));
+            }
+
+            storage.free.push(id.index);
+        }
+
+        true
+    }
+
+    pub fn is_alive(&self, id: EntityId) -> bool {
+        self.storage.read().unwrap().is_alive(id)
+    }
+
+    pub fn len(&self) -> usize {
+        let storage = self.storage.read().unwrap();
+
+        storage.slots.len() - storage.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn insert<C>(&self, id: EntityId, component: &Arc<C>) -> bool
+    where
+        C: Component,
+    {
+        let mut storage = self.storage.write().unwrap();
+
+        if storage.is_alive(id) {
+            storage.insert(id, component.clone());
+
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn get<C>(&self, id: EntityId) -> Option<Arc<C>>
+    where
+        C: Component,
+    {
+        let storage = self.storage.read().unwrap();
+
+        if storage.is_alive(id) {
+            Row {
+                storage: &storage,
+                id,
+            }
+            .first_of::<C>()
+        } else {
+            None
+        }
+    }
+
+    pub fn remove<C>(&self, id: EntityId) -> Option<Arc<C>>
+    where
+        C: Component,
+    {
+        let mut storage = self.storage.write().unwrap();
+
+        if !storage.is_alive(id) {
+            return None;
+        }
+
+        storage
+            .columns
+            .get_mut(&TypeId::of::<C>())?
+            .remove(id)
+            .and_then(|c| c.as_any().downcast::<C>().ok())
+    }
+
+    pub fn parent(&self, id: EntityId) -> Option<EntityId> {
+        let storage = self.storage.read().unwrap();
+
+        if storage.is_alive(id) {
+            storage.slots[id.index as usize].parent
+        } else {
+            None
+        }
+    }
+
+    pub fn children(&self, id: EntityId) -> Vec<EntityId> {
+        self.storage.read().unwrap().children(id)
+    }
+
+    pub fn column<C>(&self) -> Vec<(EntityId, Arc<C>)>
+    where
+        C: Component,
+    {
+        let storage = self.storage.read().unwrap();
+
+        match storage.columns.get(&TypeId::of::<C>()) {
+            Some(column) => column
+                .owners
+                .iter()
+                .zip(&column.components)
+                .filter_map(|(id, c)| Some((*id, c.clone().as_any().downcast::<C>().ok()?)))
+                .collect(),
+
+            None => Vec::new(),
+        }
+    }
+
+    pub fn query<Q>(&self) -> Vec<(EntityId, Q::Item)>
+    where
+        Q: Query,
+    {
+        let storage = self.storage.read().unwrap();
+
+        storage
+            .alive()
+            .into_iter()
+            .flat_map(|id| {
+                Q::fetch(&Row {
+                    storage: &storage,
+                    id,
+                })
+                .into_iter()
+                .map(move |item| (id, item))
+            })
+            .collect()
+    }
+
+    // One-way snapshot: components added to or removed from the `Entity` tree
+    // afterwards are not mirrored into the world, and vice versa. Trees with two
+    // components of the same type on one entity are rejected before anything is
+    // imported.
+    pub fn import(&self, entity: &Arc<Entity>) -> Result<EntityId, WorldError> {
+        for entity in entity.descendants() {
+            let components = { entity.components().read().unwrap().clone() };
+            let mut types = Vec::new();
+
+            for component in components {
+                let type_id = (*component.clone().as_any()).type_id();
+
+                if type_id == TypeId::of::<Entity>() {
+                    continue;
+                }
+
+                if types.contains(&type_id) {
+                    return Err(WorldError::DuplicateComponent {
+                        entity: entity.id.clone(),
+                        tid: component.tid(),
+                    });
+                }
+
+                types.push(type_id);
+            }
+        }
+
+        let mut storage = self.storage.write().unwrap();
+        let parent = entity
+            .entity
+            .read()
+            .unwrap()
+            .as_ref()
+            .and_then(|parent| storage.imported.get(&(Arc::as_ptr(parent) as usize)))
+            .copied();
+        let root = storage.spawn(parent);
+        let mut stack = vec![(root, entity.clone())];
+
+        while let Some((id, entity)) = stack.pop() {
+            storage.slots[id.index as usize].entity = Some(entity.clone());
+            storage.imported.insert(Arc::as_ptr(&entity) as usize, id);
+
+            let components = { entity.components().read().unwrap().clone() };
+
+            for component in components {
+                match component.clone().as_any().downcast::<Entity>() {
+                    Ok(child) => {
+                        let child_id = storage.spawn(Some(id));
+
+                        stack.push((child_id, child));
+                    }
+
+                    Err(_) => storage.insert(id, component),
+                }
+            }
+        }
+
+        Ok(root)
+    }
+
+    pub fn id_of(&self, entity: &Arc<Entity>) -> Option<EntityId> {
+        self.storage
+            .read()
+            .unwrap()
+            .imported
+            .get(&(Arc::as_ptr(entity) as usize))
+            .copied()
+    }
+
+    // Returns the imported `Entity` for the slot, or builds a detached view of
+    // its current components that leaves their existing owners untouched.
+    pub fn entity(&self, id: EntityId) -> Option<Arc<Entity>> {
+        let mut storage = self.storage.write().unwrap();
+
+        if !storage.is_alive(id) {
+            return None;
+        }
+
+        if let Some(entity) = &storage.slots[id.index as usize].entity {
+            return Some(entity.clone());
+        }
+
+        let entity = Entity::new(crate::id(&format!("{}v{}", id.index, id.generation)));
+
+        for column in storage.columns.values() {
+            if let Some(component) = column.get(id) {
+                entity.attach(component.clone());
+            }
+        }
+
+        storage.slots[id.index as usize].entity = Some(entity.clone());
+        storage.imported.insert(Arc::as_ptr(&entity) as usize, id);
+
+        Some(entity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{any::Any, sync::RwLock};
+
+    struct Tag {
+        id: Arc<String>,
+        entity: Arc<RwLock<Option<Arc<Entity>>>>,
+    }
+
+    impl Tag {
+        fn new(id: &str) -> Arc<Self> {
+            Arc::new(Self {
+                id: crate::id(id),
+                entity: crate::entity(None),
+            })
+        }
+    }
+
+    impl Component for Tag {
+        fn entity(&self) -> Arc<RwLock<Option<Arc<Entity>>>> {
+            self.entity.clone()
+        }
+
+        fn id(&self) -> Arc<String> {
+            self.id.clone()
+        }
+
+        fn tid(&self) -> Arc<String> {
+            crate::id("tag")
+        }
+
+        fn as_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync + 'static> {
+            self
+        }
+    }
+
+    #[test]
+    fn stale_handles_are_rejected() {
+        let world = World::new();
+        let stale = world.spawn();
+
+        world.insert(stale, &Tag::new("stale"));
+        world.despawn(stale);
+
+        let reused = world.spawn();
+
+        assert_eq!(reused.index, stale.index);
+        assert_ne!(reused.generation, stale.generation);
+        assert!(world.get::<Tag>(reused).is_none());
+
+        world.insert(reused, &Tag::new("reused"));
+
+        assert!(!world.is_alive(stale));
+        assert!(!world.insert(stale, &Tag::new("stale")));
+        assert!(world.get::<Tag>(stale).is_none());
+        assert!(world.remove::<Tag>(stale).is_none());
+        assert!(!world.despawn(stale));
+        assert_eq!(world.get::<Tag>(reused).unwrap().id.as_str(), "reused");
+    }
+
+    #[test]
+    fn despawn_removes_the_subtree_and_frees_slots() {
+        let world = World::new();
+        let root = world.spawn();
+        let child = world.spawn_child(root).unwrap();
+        let grandchild = world.spawn_child(child).unwrap();
+        let sibling = world.spawn_child(root).unwrap();
+
+        world.insert(grandchild, &Tag::new("grandchild"));
+
+        assert_eq!(world.children(root), vec![child, sibling]);
+        assert!(world.despawn(child));
+        assert_eq!(world.children(root), vec![sibling]);
+        assert!(!world.is_alive(grandchild));
+        assert!(world.column::<Tag>().is_empty());
+        assert_eq!(world.len(), 2);
+
+        let reused = world.spawn_child(sibling).unwrap();
+
+        assert!(reused.index == child.index || reused.index == grandchild.index);
+        assert!(world.children(child).is_empty());
+        assert_eq!(world.children(sibling), vec![reused]);
+        assert_eq!(world.parent(reused), Some(sibling));
+    }
+
+    #[test]
+    fn entity_view_keeps_component_owners() {
+        let world = World::new();
+        let entity = Entity::new(crate::id("entity"));
+        let tag = Tag::new("tag");
+
+        entity.add(&tag);
+
+        let id = world.spawn();
+
+        world.insert(id, &tag);
+
+        let view = world.entity(id).unwrap();
+
+        assert!(Arc::ptr_eq(
+            tag.entity.read().unwrap().as_ref().unwrap(),
+            &entity
+        ));
+        assert_eq!(entity.of_type::<Tag>().len(), 1);
+        assert_eq!(view.of_type::<Tag>().len(), 1);
+    }
+
+    #[test]
+    fn import_rejects_duplicate_component_types() {
+        let world = World::new();
+        let root = Entity::new(crate::id("root"));
+        let child = Entity::new(crate::id("child"));
+
+        child.add(&Tag::new("a"));
+        root.add(&child);
+
+        let id = world.import(&root).unwrap();
+        let child_id = world.children(id)[0];
+
+        assert_eq!(world.get::<Tag>(child_id).unwrap().id.as_str(), "a");
+
+        let world = World::new();
+
+        child.add(&Tag::new("b"));
+
+        match world.import(&root) {
+            Err(WorldError::DuplicateComponent { entity, tid }) => {
+                assert_eq!(entity.as_str(), "child");
+                assert_eq!(tid.as_str(), "tag");
+            }
+
+            _ => panic!("expected a duplicate component error"),
+        }
+
+        assert!(world.is_empty());
+    }
+}
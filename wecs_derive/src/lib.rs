@@ -1,12 +1,92 @@
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, DeriveInput};
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Fields, Ident};
 
-#[proc_macro_derive(Component)]
+fn named_fields(input: &DeriveInput) -> Vec<&syn::Field> {
+    match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields.named.iter().collect(),
+            _ => Vec::new(),
+        },
+        _ => Vec::new(),
+    }
+}
+
+type ReflectArgs = Option<Vec<Ident>>;
+
+fn reflect_args(attrs: &[Attribute]) -> syn::Result<ReflectArgs> {
+    match attrs.iter().find(|attr| attr.path.is_ident("reflect")) {
+        None => Ok(None),
+        Some(attr) if attr.tokens.is_empty() => Ok(Some(Vec::new())),
+        Some(attr) => attr
+            .parse_args_with(syn::punctuated::Punctuated::<Ident, syn::Token![,]>::parse_terminated)
+            .map(|args| Some(args.into_iter().collect())),
+    }
+}
+
+// Pairs every named field with its `#[reflect(...)]` arguments, or the first malformed one.
+fn reflected_fields(input: &DeriveInput) -> syn::Result<Vec<(&syn::Field, ReflectArgs)>> {
+    named_fields(input)
+        .into_iter()
+        .map(|field| Ok((field, reflect_args(&field.attrs)?)))
+        .collect()
+}
+
+#[proc_macro_derive(Component, attributes(reflect))]
 pub fn component(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
+    // `#[reflect(a, b)]` on a component field names methods to call after a reflected write.
+    let fields = match reflected_fields(&input) {
+        Ok(fields) => fields,
+        Err(e) => return TokenStream::from(e.to_compile_error()),
+    };
+    let (reflected, notify): (Vec<_>, Vec<_>) = fields
+        .into_iter()
+        .filter_map(|(field, args)| Some((field.ident.clone()?, args?)))
+        .unzip();
     let name = input.ident;
     let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let reflection = if reflected.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            fn fields(&self) -> Vec<Field> {
+                let mut fields = Vec::new();
+
+                #(fields.extend(Reflect::fields(&*self.#reflected.read().unwrap()));)*
+
+                fields
+            }
+
+            fn get_field(&self, name: &str) -> Option<Value> {
+                #(
+                    if let Some(value) = Reflect::get_field(&*self.#reflected.read().unwrap(), name) {
+                        return Some(value);
+                    }
+                )*
+
+                None
+            }
+
+            fn set_field(&self, name: &str, value: Value) -> Result<(), ReflectError> {
+                #(
+                    let result = Reflect::set_field(&mut *self.#reflected.write().unwrap(), name, value.clone());
+
+                    match result {
+                        Err(ReflectError::UnknownField(_)) => {}
+                        Ok(()) => {
+                            #(self.#notify();)*
+
+                            return Ok(());
+                        }
+                        result => return result,
+                    }
+                )*
+
+                Err(ReflectError::UnknownField(name.to_string()))
+            }
+        }
+    };
     let expanded = quote! {
         impl #impl_generics Component for #name #ty_generics #where_clause {
             fn entity(&self) -> Arc<RwLock<Option<Arc<Entity>>>> {
@@ -24,6 +104,77 @@ pub fn component(input: TokenStream) -> TokenStream {
             fn as_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync + 'static> {
                 self.clone()
             }
+
+            #reflection
+        }
+    };
+
+    TokenStream::from(expanded)
+}
+
+#[proc_macro_derive(Reflect, attributes(reflect))]
+pub fn reflect(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let fields = match reflected_fields(&input) {
+        Ok(fields) => fields,
+        Err(e) => return TokenStream::from(e.to_compile_error()),
+    };
+    let mut kept = Vec::new();
+
+    for (field, args) in fields {
+        let args = args.unwrap_or_default();
+
+        if let Some(arg) = args.iter().find(|arg| *arg != "skip") {
+            return TokenStream::from(
+                syn::Error::new(arg.span(), "expected `skip`").to_compile_error(),
+            );
+        }
+
+        if args.is_empty() {
+            kept.push(field);
+        }
+    }
+
+    let fields = kept;
+    let idents = fields
+        .iter()
+        .filter_map(|field| field.ident.clone())
+        .collect::<Vec<_>>();
+    let names = idents
+        .iter()
+        .map(|ident| ident.to_string())
+        .collect::<Vec<_>>();
+    let types = fields
+        .iter()
+        .map(|field| {
+            let ty = &field.ty;
+
+            quote!(#ty).to_string().replace(' ', "")
+        })
+        .collect::<Vec<_>>();
+    let name = input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let expanded = quote! {
+        impl #impl_generics Reflect for #name #ty_generics #where_clause {
+            fn fields(&self) -> Vec<Field> {
+                vec![#(Field::new(#names, #types)),*]
+            }
+
+            fn get_field(&self, name: &str) -> Option<Value> {
+                match name {
+                    #(#names => reflect::to_value(&self.#idents),)*
+                    _ => None,
+                }
+            }
+
+            fn set_field(&mut self, name: &str, value: Value) -> Result<(), ReflectError> {
+                match name {
+                    #(#names => self.#idents = reflect::from_value(name, value)?,)*
+                    _ => return Err(ReflectError::UnknownField(name.to_string())),
+                }
+
+                Ok(())
+            }
         }
     };
 